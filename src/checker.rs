@@ -0,0 +1,246 @@
+use errors::Error;
+use models::binary_model::is_binary_model;
+use models::probability_model::{read_text_dump, ProbabilityModel};
+use models::sigma::{Sigma, SigmaType};
+use models::symbol::UNSEEN_SYMBOL;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Problem found in a language model by `check_model`
+///
+/// # InvalidProbability
+///
+/// An ngram holds a probability that is NaN, infinite, zero, negative or above one, e.g. zero
+/// probabilities of unsmoothed models.
+///
+/// # Denormalized
+///
+/// The probabilities of the symbols following a prefix (context) don't sum up to one.
+///
+/// # Missing
+///
+/// A symbol of the declared alphabet has no unigram probability, or ngrams of a length up to
+/// the declared ngram length can't be looked up at all.
+///
+/// # Duplicate
+///
+/// An ngram is held more than once by the model dump, only one of the values is used.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Issue {
+    InvalidProbability { ngram: String, probability: f64 },
+    Denormalized { prefix: String, sum: f64 },
+    Missing { ngram: String },
+    Duplicate { ngram: String, occurences: usize },
+}
+
+/// Format issue as a single report line, the placeholder of unseen symbols is escaped
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Issue::InvalidProbability { ngram, probability } => write!(
+                f,
+                "invalid probability of ngram '{}': {}",
+                escape(ngram),
+                probability
+            ),
+            Issue::Denormalized { prefix, sum } => write!(
+                f,
+                "probabilities following prefix '{}' sum up to {}",
+                escape(prefix),
+                sum
+            ),
+            Issue::Missing { ngram } => write!(f, "missing ngram '{}'", escape(ngram)),
+            Issue::Duplicate { ngram, occurences } => write!(
+                f,
+                "duplicate ngram '{}' held {} times",
+                escape(ngram),
+                occurences
+            ),
+        }
+    }
+}
+
+/// Check a language model dump for consistency
+///
+/// The checks are:
+///
+/// * every probability is a finite number in (0, 1]
+/// * the distribution following each prefix sums up to one within `tolerance`, see
+///   `ProbabilityModel::check_normalization`
+/// * every symbol of the alphabet declared in the model header has a unigram probability, and
+///   ngrams of every length up to the declared ngram length can be looked up (for models
+///   without header, up to the longest ngram held, and `corpus` alphabets are skipped)
+/// * no ngram is held more than once
+///
+/// # Arguments
+///
+/// * `path` - path to the text or binary model dump
+/// * `tolerance` - maximal deviation of a prefix's sum from one
+///
+/// Returns the issues found, an empty list if the model passed all checks.
+pub fn check_model(path: &str, tolerance: f64) -> Result<Vec<Issue>, Error> {
+    let model = ProbabilityModel::from_file(path)?;
+    // the table of a text dump keeps only one value per ngram, so its lines are read again
+    let is_binary: bool = is_binary_model(path).map_err(|err| err.in_file(path))?;
+    let entries: Vec<(String, f64)> = match is_binary {
+        true => model.entries(),
+        false => read_text_dump(path).map_err(|err| err.in_file(path))?.1,
+    };
+    let mut issues: Vec<Issue> = Vec::new();
+    let mut occurences: BTreeMap<&str, usize> = BTreeMap::new();
+    for (ngram, probability) in entries.iter() {
+        *occurences.entry(&ngram[..]).or_insert(0) += 1;
+        if !(probability.is_finite() && *probability > 0.0 && *probability <= 1.0) {
+            issues.push(Issue::InvalidProbability {
+                ngram: ngram.clone(),
+                probability: *probability,
+            });
+        }
+    }
+    issues.extend(
+        model
+            .check_normalization(tolerance)
+            .into_iter()
+            .map(|(prefix, sum)| Issue::Denormalized { prefix, sum }),
+    );
+    issues.extend(missing_ngrams(&model, &entries)?);
+    issues.extend(
+        occurences
+            .into_iter()
+            .filter(|(_, occurences)| *occurences > 1)
+            .map(|(ngram, occurences)| Issue::Duplicate {
+                ngram: String::from(ngram),
+                occurences,
+            }),
+    );
+    Ok(issues)
+}
+
+/// Find the unigrams of the declared alphabet and the ngram lengths the model can't look up
+///
+/// Ngrams of a length are looked up as the ngram made of `UNSEEN_SYMBOL` only, which every
+/// ngram with an unseen prefix resolves to. Alphabets collected from the corpus aren't known in
+/// advance, so their unigrams aren't checked.
+fn missing_ngrams(
+    model: &ProbabilityModel,
+    entries: &[(String, f64)],
+) -> Result<Vec<Issue>, Error> {
+    let mut missing: Vec<Issue> = Vec::new();
+    let unseen: String = UNSEEN_SYMBOL.to_string();
+    let ngram_length: usize = match &model.header {
+        Some(header) => {
+            let mut symbols: Vec<String> = match header.sigma_type {
+                SigmaType::Corpus => Vec::new(),
+                _ => Sigma::new(header.set_marker, header.sigma_type.clone()).as_string_vec()?,
+            };
+            symbols.sort();
+            missing.extend(
+                symbols
+                    .into_iter()
+                    .filter(|symbol| model.get(symbol).is_none())
+                    .map(|ngram| Issue::Missing { ngram }),
+            );
+            header.ngram_length
+        }
+        None => entries
+            .iter()
+            .map(|(ngram, _)| ngram.chars().count())
+            .max()
+            .unwrap_or(1),
+    };
+    missing.extend(
+        (2..=ngram_length)
+            .map(|length| unseen.repeat(length))
+            .filter(|ngram| model.get(ngram).is_none())
+            .map(|ngram| Issue::Missing { ngram }),
+    );
+    Ok(missing)
+}
+
+/// Escape control characters of an ngram and its placeholder of unseen symbols for printing
+fn escape(ngram: &str) -> String {
+    ngram
+        .escape_debug()
+        .to_string()
+        .replace(UNSEEN_SYMBOL, "\\u{fffd}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use models::probability_model::NORMALIZATION_TOLERANCE;
+    use smoothing::SmoothingType;
+    use std::fs;
+    use trainer::ModelTrainer;
+
+    #[test]
+    fn test_check_model1() {
+        let path = std::env::temp_dir().join("naive_langguesser_check_test1.model");
+        let path = path.to_str().unwrap();
+        let sigma: Sigma = Sigma::new(None, SigmaType::Test);
+        for smoothing_type in [
+            SmoothingType::WittenBellSmoothing,
+            SmoothingType::KneserNeySmoothing,
+        ]
+        .iter()
+        {
+            let trainer = ModelTrainer::new("test", sigma.clone(), 3, smoothing_type.clone());
+            let model = trainer.train(&["aabcbaabaaac"]).unwrap();
+            model.write_to_file(path).unwrap();
+            let issues = check_model(path, NORMALIZATION_TOLERANCE).unwrap();
+            assert_eq!(Vec::<Issue>::new(), issues, "smoothing: {}", smoothing_type.name());
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_check_model2() {
+        let path = std::env::temp_dir().join("naive_langguesser_check_test2.model");
+        let path = path.to_str().unwrap();
+        fs::write(path, "a\t0.5\nb\t0.5\nc\t0\nab\tNaN\nab\t1\nba\t0.5\n").unwrap();
+        let issues = check_model(path, NORMALIZATION_TOLERANCE).unwrap();
+        fs::remove_file(path).unwrap();
+        assert!(issues.contains(&Issue::InvalidProbability {
+            ngram: String::from("c"),
+            probability: 0.0
+        }));
+        assert!(issues
+            .iter()
+            .any(|issue| match issue {
+                Issue::InvalidProbability { ngram, probability } => {
+                    ngram == "ab" && probability.is_nan()
+                }
+                _ => false,
+            }));
+        assert!(issues.contains(&Issue::Denormalized {
+            prefix: String::from("b"),
+            sum: 0.5
+        }));
+        assert!(issues.contains(&Issue::Missing {
+            ngram: String::from("\u{FFFD}\u{FFFD}")
+        }));
+        assert!(issues.contains(&Issue::Duplicate {
+            ngram: String::from("ab"),
+            occurences: 2
+        }));
+        assert_eq!(
+            "duplicate ngram 'ab' held 2 times",
+            format!("{}", issues.last().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_check_model3() {
+        let path = std::env::temp_dir().join("naive_langguesser_check_test3.model");
+        let path = path.to_str().unwrap();
+        let sigma: Sigma = Sigma::new(None, SigmaType::Ascii);
+        let corpus: String = fs::read_to_string("./data/english.txt").unwrap();
+        let smoothing_type = SmoothingType::GoodTuringSmoothing;
+        let trainer = ModelTrainer::new("english", sigma, 3, smoothing_type);
+        trainer.train(&[&corpus]).unwrap().write_to_file(path).unwrap();
+        // every prefix leaves some probability to unseen ngrams, so none scores zero or -inf
+        let issues = check_model(path, NORMALIZATION_TOLERANCE).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(Vec::<Issue>::new(), issues);
+    }
+}
@@ -1,21 +1,37 @@
+use errors::Error;
+use models::probability_model::{ModelFormat, NORMALIZATION_TOLERANCE};
 use models::sigma::{Sigma, SigmaType};
+use inferer::Thresholds;
+use output::OutputFormat;
+use scoring::ScoringType;
+use std::collections::HashMap;
+use std::env;
+use std::path::Path;
+use std::str::FromStr;
+use std::thread;
+use utils::RecordSeparator;
 use smoothing::SmoothingType;
 use Mode;
 
+/// Environment variable holding the directory of the language models
+pub const MODEL_DIR_VAR: &str = "NAIVE_LANGGUESSER_MODEL_DIR";
+
 /// Hold configuration for `Model` mode
 ///
 /// # Fields
 ///
-/// * `filename` - path to the the file holding a text example of the language
+/// * `paths` - paths to files holding text examples of the language, directories or glob patterns
 /// * `modelname` - name of the language model (relevant for name of language file representation)
-/// * `outpath` - folder path to write the language model to
+/// * `outpath` - file path to write the language model to
 /// * `application_mode` - `Model` mode setting
 /// * `sigma_id` - specification for preprocessing the text example file
 /// * `ngram_length` - max ngram length for calculating the language model
 /// * `set_marker` - if set, add text begin/end marker (flag and marker symbol in one field)
-/// * `smoothing_type` - set type of smoothing for ngram counts
+/// * `smoothing_type` - set type of smoothing for ngram counts, lidstone smoothing without
+///   pseudo-counts tunes them on held-out lines of the text examples
+/// * `held_out` - if set, path to held-out text to estimate interpolation weights on
 pub struct ModelConfig {
-    pub filename: String,
+    pub paths: Vec<String>,
     pub modelname: String,
     pub outpath: String,
     pub application_mode: Mode,
@@ -23,6 +39,7 @@ pub struct ModelConfig {
     pub ngram_length: usize,
     pub set_marker: Option<u8>,
     pub smoothing_type: SmoothingType,
+    pub held_out: Option<String>,
 }
 
 impl ModelConfig {
@@ -31,40 +48,36 @@ impl ModelConfig {
     /// # Arguments
     ///
     /// * `matches` - `Clap` references holding cli arguments
-    pub fn new(matches: &clap::ArgMatches) -> ModelConfig {
-        let filename = matches.value_of("path").unwrap().to_string();
+    pub fn new(matches: &clap::ArgMatches) -> Result<ModelConfig, Error> {
+        let paths: Vec<String> = match matches.values_of("path") {
+            Some(paths) => paths.map(String::from).collect(),
+            None => Vec::new(),
+        };
         let modelname = matches.value_of("model-name").unwrap().to_string();
-        let outpath = format!(
-            "data/models/{}/{}.model",
-            matches.value_of("alphabet").unwrap(),
-            matches.value_of("model-name").unwrap().to_string()
-        );
-        let application_mode = Mode::Model;
-        let ngram_length = matches
-            .value_of("n-gram-length")
-            .unwrap()
-            .to_string()
-            .parse::<usize>()
-            .unwrap();
-        let sigma_type: SigmaType = match matches.value_of("alphabet").unwrap() {
-            "alphanum" => SigmaType::AlphaNum,
-            "ascii" => SigmaType::Ascii,
-            _ => panic!("Alphabet is not implemented"),
+        let outpath: String = match matches.value_of("output") {
+            Some(outpath) => String::from(outpath),
+            None => Path::new(&model_dir(matches))
+                .join(format!("{}.model", modelname))
+                .to_string_lossy()
+                .into_owned(),
         };
+        let application_mode = Mode::Model;
+        let ngram_length: usize = ngram_length(matches)?;
+        let sigma_type: SigmaType = sigma_type(matches)?;
         let marker_symbol: u8 = 35;
         let set_marker: Option<u8> = match matches.is_present("set-marker") {
             true => Some(marker_symbol),
             false => None,
         };
         let sigma: Sigma = Sigma::new(set_marker, sigma_type);
-        let smoothing_type: SmoothingType = match matches.value_of("smoothing-type").unwrap() {
-            "no" => SmoothingType::NoSmoothing,
-            "add_one" => SmoothingType::AddOneSmoothing,
-            "witten_bell" => SmoothingType::WittenBellSmoothing,
-            _ => panic!("Smoothing type is unknown"),
-        };
-        return ModelConfig {
-            filename,
+        let smoothing_type: SmoothingType =
+            match SmoothingType::from_name(matches.value_of("smoothing-type").unwrap()) {
+                Some(smoothing_type) => pseudo_counts(matches, smoothing_type)?,
+                None => return Err(Error::config("Smoothing type is unknown")),
+            };
+        let held_out: Option<String> = matches.value_of("held-out").map(String::from);
+        Ok(ModelConfig {
+            paths,
             modelname,
             outpath,
             application_mode,
@@ -72,7 +85,8 @@ impl ModelConfig {
             ngram_length,
             set_marker,
             smoothing_type,
-        };
+            held_out,
+        })
     }
 }
 
@@ -80,21 +94,45 @@ impl ModelConfig {
 ///
 /// # Fields
 ///
-/// * `filename` - path to the the file holding a text for language classification
+/// * `paths` - paths to files holding texts for language classification, directories or glob
+///   patterns
 /// * `model_dir` - directory holding present language models
 /// * `application_mode` - `Guess` mode setting
 /// * `sigma_id` - specification for preprocessing the text file
 /// * `ngram_length` - max ngram length for text language classification
 /// * `set_marker` - if set, add text begin/end marker (flag and marker symbol in one field)
 /// * `in_parallel` - if set, causes parallel language model evaluation of text
+/// * `scoring_type` - how ngrams are scored against the language models
+/// * `priors` - prior probabilities of the language models by model name
+/// * `thresholds` - thresholds for rejecting the best ranked language model as unknown or
+///   undetermined
+/// * `segment` - if set, split the text into segments of different language
+/// * `switch_penalty` - cost in bits of switching the language between segments
+/// * `jobs` - count of worker threads guessing files
+/// * `separator` - if set, read records separated this way from stdin instead of files
+/// * `output_format` - format results are written in
+/// * `top_k` - if set, count of the best ranked language models to report
+/// * `skip_invalid_models` - if set, skip unreadable or unfitting language models with a warning
+/// * `verbose` - if set, add verdict, per ngram score and posterior to the text output
 pub struct GuessConfig {
-    pub filename: String,
+    pub paths: Vec<String>,
     pub model_dir: String,
     pub application_mode: Mode,
     pub sigma: Sigma,
     pub ngram_length: usize,
     pub set_marker: Option<u8>,
     pub in_parallel: bool,
+    pub scoring_type: ScoringType,
+    pub priors: HashMap<String, f64>,
+    pub thresholds: Thresholds,
+    pub segment: bool,
+    pub switch_penalty: f64,
+    pub jobs: usize,
+    pub separator: Option<RecordSeparator>,
+    pub output_format: OutputFormat,
+    pub top_k: Option<usize>,
+    pub skip_invalid_models: bool,
+    pub verbose: bool,
 }
 
 impl GuessConfig {
@@ -103,24 +141,15 @@ impl GuessConfig {
     /// # Arguments
     ///
     /// * `matches` - `Clap` references holding cli arguments
-    pub fn new(matches: &clap::ArgMatches) -> GuessConfig {
-        let filename = matches.value_of("path").unwrap().to_string();
-        let model_dir = format!(
-            "./data/models/{}/",
-            matches.value_of("alphabet").unwrap()
-        );
-        let application_mode = Mode::Guess;
-        let ngram_length = matches
-            .value_of("n-gram-length")
-            .unwrap()
-            .to_string()
-            .parse::<usize>()
-            .unwrap();
-        let sigma_type: SigmaType = match matches.value_of("alphabet").unwrap() {
-            "alphanum" => SigmaType::AlphaNum,
-            "ascii" => SigmaType::Ascii,
-            _ => panic!("Alphabet is not implemented"),
+    pub fn new(matches: &clap::ArgMatches) -> Result<GuessConfig, Error> {
+        let paths: Vec<String> = match matches.values_of("path") {
+            Some(paths) => paths.map(String::from).collect(),
+            None => Vec::new(),
         };
+        let model_dir: String = model_dir(matches);
+        let application_mode = Mode::Guess;
+        let ngram_length: usize = ngram_length(matches)?;
+        let sigma_type: SigmaType = sigma_type(matches)?;
         let marker_symbol: u8 = 35;
         let set_marker: Option<u8> = match matches.is_present("set-marker") {
             true => Some(marker_symbol),
@@ -128,14 +157,310 @@ impl GuessConfig {
         };
         let sigma: Sigma = Sigma::new(set_marker, sigma_type);
         let in_parallel: bool = matches.is_present("in-parallel");
-        return GuessConfig {
-            filename,
+        let mut scoring_type: ScoringType =
+            match ScoringType::from_name(matches.value_of("scoring").unwrap()) {
+                Some(scoring_type) => scoring_type,
+                None => return Err(Error::config("Scoring type is unknown")),
+            };
+        if let Some(raw) = matches.value_of("lambdas") {
+            let lambdas: Vec<f64> = raw
+                .split(',')
+                .map(|lambda| lambda.trim().parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|_| Error::config(&format!("Illformed interpolation weights '{}'", raw)))?;
+            scoring_type = match scoring_type {
+                ScoringType::Interpolation(_) => ScoringType::Interpolation(Some(lambdas)),
+                _ => {
+                    return Err(Error::config(
+                        "Interpolation weights require interpolation scoring",
+                    ))
+                }
+            };
+        }
+        let mut priors: HashMap<String, f64> = HashMap::new();
+        if let Some(raw) = matches.value_of("priors") {
+            for pair in raw.split(',') {
+                let mut split = pair.splitn(2, '=');
+                let name: String = split.next().unwrap().trim().to_string();
+                let prior: f64 = split
+                    .next()
+                    .and_then(|prior| prior.trim().parse::<f64>().ok())
+                    .filter(|prior| *prior > 0.0)
+                    .ok_or_else(|| {
+                        Error::config(&format!(
+                            "Illformed prior '{}', expected <model_name>=<positive number>",
+                            pair
+                        ))
+                    })?;
+                priors.insert(name, prior);
+            }
+        }
+        let thresholds = Thresholds {
+            min_score: parse_arg(matches, "min-score")?,
+            min_margin: parse_arg(matches, "min-margin")?,
+            min_symbols: parse_arg(matches, "min-symbols")?.unwrap_or(0),
+        };
+        let segment: bool = matches.is_present("segment");
+        let switch_penalty: f64 = parse_arg(matches, "switch-penalty")?.unwrap_or(0.0);
+        let jobs: usize = match parse_arg(matches, "jobs")? {
+            Some(jobs) => jobs,
+            None => thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+        };
+        let separator: Option<RecordSeparator> = match matches.is_present("stdin") {
+            true => match RecordSeparator::from_name(matches.value_of("separator").unwrap()) {
+                Some(separator) => Some(separator),
+                None => return Err(Error::config("Record separator is unknown")),
+            },
+            false => None,
+        };
+        let output_format: OutputFormat =
+            match OutputFormat::from_name(matches.value_of("output-format").unwrap()) {
+                Some(output_format) => output_format,
+                None => return Err(Error::config("Output format is unknown")),
+            };
+        let top_k: Option<usize> = match matches.is_present("best-only") {
+            true => Some(1),
+            false => match parse_arg::<usize>(matches, "top-k")? {
+                Some(0) => return Err(Error::config("Top k must be a positive count")),
+                top_k => top_k,
+            },
+        };
+        let skip_invalid_models: bool = matches.is_present("skip-invalid-models");
+        let verbose: bool = matches.is_present("verbose");
+        Ok(GuessConfig {
+            paths,
             model_dir,
             application_mode,
             sigma,
             ngram_length,
             set_marker,
             in_parallel,
+            scoring_type,
+            priors,
+            thresholds,
+            segment,
+            switch_penalty,
+            jobs,
+            separator,
+            output_format,
+            top_k,
+            skip_invalid_models,
+            verbose,
+        })
+    }
+}
+
+/// Hold configuration for `Update` mode
+///
+/// # Fields
+///
+/// * `model_path` - path to the language model to update, its raw counts are read from the
+///   `.counts` file next to it
+/// * `paths` - paths to files holding additional text examples, directories or glob patterns
+/// * `application_mode` - `Update` mode setting
+/// * `smoothing_type` - if set, smoothing replacing the one of the model
+/// * `held_out` - if set, path to held-out text to re-estimate interpolation weights on
+pub struct UpdateConfig {
+    pub model_path: String,
+    pub paths: Vec<String>,
+    pub application_mode: Mode,
+    pub smoothing_type: Option<SmoothingType>,
+    pub held_out: Option<String>,
+}
+
+impl UpdateConfig {
+    /// Collect and parse cli arguments of `Update` mode
+    ///
+    /// # Arguments
+    ///
+    /// * `matches` - `Clap` references holding cli arguments
+    pub fn new(matches: &clap::ArgMatches) -> Result<UpdateConfig, Error> {
+        let model_path = matches.value_of("model").unwrap().to_string();
+        let paths: Vec<String> = match matches.values_of("path") {
+            Some(paths) => paths.map(String::from).collect(),
+            None => Vec::new(),
         };
+        let application_mode = Mode::Update;
+        let smoothing_type: Option<SmoothingType> = match matches.value_of("smoothing-type") {
+            Some(name) => match SmoothingType::from_name(name) {
+                Some(smoothing_type) => Some(pseudo_counts(matches, smoothing_type)?),
+                None => return Err(Error::config("Smoothing type is unknown")),
+            },
+            None if matches.is_present("pseudo-counts") => {
+                return Err(Error::config("Pseudo-counts require lidstone smoothing"))
+            }
+            None => None,
+        };
+        let held_out: Option<String> = matches.value_of("held-out").map(String::from);
+        Ok(UpdateConfig {
+            model_path,
+            paths,
+            application_mode,
+            smoothing_type,
+            held_out,
+        })
     }
 }
+
+/// Hold configuration for `Convert` mode
+///
+/// # Fields
+///
+/// * `input` - path to the language model to convert
+/// * `output` - path to write the converted language model to
+/// * `application_mode` - `Convert` mode setting
+/// * `format` - format of the converted language model
+pub struct ConvertConfig {
+    pub input: String,
+    pub output: String,
+    pub application_mode: Mode,
+    pub format: ModelFormat,
+}
+
+impl ConvertConfig {
+    /// Collect and parse cli arguments of `Convert` mode
+    ///
+    /// # Arguments
+    ///
+    /// * `matches` - `Clap` references holding cli arguments
+    pub fn new(matches: &clap::ArgMatches) -> Result<ConvertConfig, Error> {
+        let input = matches.value_of("input").unwrap().to_string();
+        let output = matches.value_of("output").unwrap().to_string();
+        let application_mode = Mode::Convert;
+        let format: ModelFormat = match ModelFormat::from_name(matches.value_of("format").unwrap()) {
+            Some(format) => format,
+            None => return Err(Error::config("Model format is unknown")),
+        };
+        Ok(ConvertConfig {
+            input,
+            output,
+            application_mode,
+            format,
+        })
+    }
+}
+
+/// Hold configuration for `Check` mode
+///
+/// # Fields
+///
+/// * `model_paths` - paths to the language models to check
+/// * `application_mode` - `Check` mode setting
+/// * `tolerance` - maximal deviation from one of the probabilities following a prefix summed up
+pub struct CheckConfig {
+    pub model_paths: Vec<String>,
+    pub application_mode: Mode,
+    pub tolerance: f64,
+}
+
+impl CheckConfig {
+    /// Collect and parse cli arguments of `Check` mode
+    ///
+    /// # Arguments
+    ///
+    /// * `matches` - `Clap` references holding cli arguments
+    pub fn new(matches: &clap::ArgMatches) -> Result<CheckConfig, Error> {
+        let model_paths: Vec<String> = matches
+            .values_of("model")
+            .unwrap()
+            .map(String::from)
+            .collect();
+        let application_mode = Mode::Check;
+        let tolerance: f64 = parse_arg(matches, "tolerance")?.unwrap_or(NORMALIZATION_TOLERANCE);
+        if tolerance.is_nan() || tolerance < 0.0 {
+            return Err(Error::config("Tolerance must not be negative"));
+        }
+        Ok(CheckConfig {
+            model_paths,
+            application_mode,
+            tolerance,
+        })
+    }
+}
+
+/// Resolve the directory of the language models
+///
+/// Taken from `--model-dir`, otherwise from the `MODEL_DIR_VAR` environment variable, otherwise
+/// it is `./data/models/<alphabet>/`.
+///
+/// # Arguments
+///
+/// * `matches` - `Clap` references holding cli arguments
+fn model_dir(matches: &clap::ArgMatches) -> String {
+    if let Some(model_dir) = matches.value_of("model-dir") {
+        return String::from(model_dir);
+    }
+    match env::var(MODEL_DIR_VAR) {
+        Ok(model_dir) if !model_dir.is_empty() => model_dir,
+        _ => format!("./data/models/{}/", matches.value_of("alphabet").unwrap()),
+    }
+}
+
+/// Apply pseudo-counts of lidstone smoothing given as comma separated list, shortest ngram
+/// length first
+fn pseudo_counts(
+    matches: &clap::ArgMatches,
+    smoothing_type: SmoothingType,
+) -> Result<SmoothingType, Error> {
+    let raw: &str = match matches.value_of("pseudo-counts") {
+        Some(raw) => raw,
+        None => return Ok(smoothing_type),
+    };
+    let pseudo_counts: Vec<f64> = raw
+        .split(',')
+        .map(|pseudo_count| pseudo_count.trim().parse::<f64>())
+        .collect::<Result<Vec<f64>, _>>()
+        .ok()
+        .filter(|pseudo_counts| {
+            pseudo_counts
+                .iter()
+                .all(|pseudo_count| pseudo_count.is_finite() && *pseudo_count >= 0.0)
+        })
+        .ok_or_else(|| Error::config(&format!("Illformed pseudo-counts '{}'", raw)))?;
+    match smoothing_type {
+        SmoothingType::LidstoneSmoothing(_) => {
+            Ok(SmoothingType::LidstoneSmoothing(Some(pseudo_counts)))
+        }
+        _ => Err(Error::config("Pseudo-counts require lidstone smoothing")),
+    }
+}
+
+/// Parse the value of a cli argument, if given
+///
+/// # Arguments
+///
+/// * `matches` - `Clap` references holding cli arguments
+/// * `name` - name of the cli argument
+fn parse_arg<T: FromStr>(matches: &clap::ArgMatches, name: &str) -> Result<Option<T>, Error> {
+    match matches.value_of(name) {
+        Some(raw) => raw
+            .trim()
+            .parse::<T>()
+            .map(Some)
+            .map_err(|_| Error::config(&format!("Illformed value '{}' of --{}", raw, name))),
+        None => Ok(None),
+    }
+}
+
+/// Parse the ngram length, which must be positive
+///
+/// # Arguments
+///
+/// * `matches` - `Clap` references holding cli arguments
+fn ngram_length(matches: &clap::ArgMatches) -> Result<usize, Error> {
+    match parse_arg::<usize>(matches, "n-gram-length")? {
+        Some(0) | None => Err(Error::config("N-gram length must be a positive count")),
+        Some(ngram_length) => Ok(ngram_length),
+    }
+}
+
+/// Parse the alphabet
+///
+/// # Arguments
+///
+/// * `matches` - `Clap` references holding cli arguments
+fn sigma_type(matches: &clap::ArgMatches) -> Result<SigmaType, Error> {
+    let name: &str = matches.value_of("alphabet").unwrap_or_default();
+    SigmaType::from_name(name)
+        .ok_or_else(|| Error::config(&format!("Alphabet '{}' is not implemented", name)))
+}
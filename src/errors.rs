@@ -1,221 +1,243 @@
-use models::errors::CountModelError;
-use models::errors::{ProbabilityModelError, TextError};
-use std::error::Error;
+use models::sigma::SigmaType;
+use std::error::Error as StdError;
 use std::fmt;
-use std::io::Error as IOError;
+use std::io;
+use std::str::Utf8Error;
 
+/// Error of the language guesser
+///
+/// Variants carry the context needed to react on them, e.g. the path and line of a malformed
+/// model file. Underlying errors are kept and provided through `source`.
 #[derive(Debug)]
-pub struct ModellingError {
-    details: String,
-}
-
-impl ModellingError {
-    pub fn new(msg: &str) -> ModellingError {
-        ModellingError {
-            details: msg.to_string(),
+pub enum Error {
+    /// Reading or writing a file failed
+    Io {
+        path: Option<String>,
+        source: io::Error,
+    },
+    /// A model file is malformed, `line` counts from one
+    ModelFormat {
+        path: Option<String>,
+        line: Option<usize>,
+        details: String,
+        source: Option<Box<dyn StdError + Send + Sync>>,
+    },
+    /// A model is based on another alphabet than the one used for guessing
+    AlphabetMismatch {
+        model: String,
+        model_sigma: SigmaType,
+        sigma: SigmaType,
+    },
+    /// A model differs from the text marker setting used for guessing
+    MarkerMismatch { model: String },
+    /// A model holds shorter ngrams than the ones used for guessing
+    NGramLengthMismatch {
+        model: String,
+        model_length: usize,
+        ngram_length: usize,
+    },
+    /// An ngram isn't part of the vocabulary of a model
+    UnknownNGram { ngram: String },
+    /// The bytes of a symbol aren't valid utf-8
+    InvalidSymbol { bytes: Vec<u8>, source: Utf8Error },
+    /// A path or glob pattern doesn't resolve to files
+    Path {
+        details: String,
+        source: Option<Box<dyn StdError + Send + Sync>>,
+    },
+    /// A setting is unknown or ill-formed
+    Config { details: String },
+    /// A model can't be built or changed as requested
+    Model { details: String },
+    /// Some texts of a batch couldn't be guessed
+    Batch { failed: usize, total: usize },
+}
+
+impl Error {
+    pub fn model_format(details: &str) -> Error {
+        Error::ModelFormat {
+            path: None,
+            line: None,
+            details: details.to_string(),
+            source: None,
         }
     }
-}
-
-impl fmt::Display for ModellingError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.details)
-    }
-}
-
-impl Error for ModellingError {
-    fn description(&self) -> &str {
-        &self.details
-    }
-}
-
-impl From<IOError> for ModellingError {
-    fn from(err: IOError) -> Self {
-        let desc = format!("File reading error: {}", err.to_string());
-        ModellingError::new(&desc[..])
-    }
-}
-
-impl From<TextError> for ModellingError {
-    fn from(err: TextError) -> Self {
-        let desc = format!("Text processing error: {}", err.to_string());
-        ModellingError::new(&desc[..])
-    }
-}
-
-impl From<CountModelError> for ModellingError {
-    fn from(err: CountModelError) -> Self {
-        let desc = format!("Counting error: {}", err.to_string());
-        ModellingError::new(&desc[..])
-    }
-}
-
-impl From<ProbabilityModelError> for ModellingError {
-    fn from(err: ProbabilityModelError) -> Self {
-        let desc = format!("Error during probability calculation: {}", err.to_string());
-        ModellingError::new(&desc[..])
-    }
-}
 
-#[derive(Debug)]
-pub struct GuessingError {
-    details: String,
-}
-
-impl GuessingError {
-    pub fn new(msg: &str) -> GuessingError {
-        GuessingError {
-            details: msg.to_string(),
+    pub fn path(details: &str) -> Error {
+        Error::Path {
+            details: details.to_string(),
+            source: None,
         }
     }
-}
-
-impl fmt::Display for GuessingError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.details)
-    }
-}
-
-impl Error for GuessingError {
-    fn description(&self) -> &str {
-        &self.details
-    }
-}
 
-impl From<IOError> for GuessingError {
-    fn from(err: IOError) -> Self {
-        let desc = format!("io::Error: {}", err.to_string());
-        GuessingError::new(&desc[..])
-    }
-}
-
-impl From<InfererError> for GuessingError {
-    fn from(err: InfererError) -> Self {
-        let desc = format!("InfererError: {}", err.to_string());
-        GuessingError::new(&desc[..])
-    }
-}
-
-impl From<TextError> for GuessingError {
-    fn from(err: TextError) -> Self {
-        let desc = format!("Text processing error: {}", err.to_string());
-        GuessingError::new(&desc[..])
-    }
-}
-
-#[derive(Debug)]
-pub struct InfererError {
-    details: String,
-}
-
-impl InfererError {
-    pub fn new(msg: &str) -> InfererError {
-        InfererError {
-            details: msg.to_string(),
+    pub fn config(details: &str) -> Error {
+        Error::Config {
+            details: details.to_string(),
         }
     }
-}
-
-impl fmt::Display for InfererError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.details)
-    }
-}
-
-impl Error for InfererError {
-    fn description(&self) -> &str {
-        &self.details
-    }
-}
-
-impl From<UtilError> for InfererError {
-    fn from(err: UtilError) -> Self {
-        let desc = format!("UtilError: {}", err.to_string());
-        InfererError::new(&desc[..])
-    }
-}
-
-impl From<ProbabilityModelError> for InfererError {
-    fn from(err: ProbabilityModelError) -> Self {
-        let desc = format!("ProbabilityModelError: {}", err.to_string());
-        InfererError::new(&desc[..])
-    }
-}
-
-
-#[derive(Debug)]
-pub struct UtilError {
-    details: String
-}
-
-impl UtilError {
-    pub fn new(msg: &str) -> UtilError {
-        UtilError{details: msg.to_string()}
-    }
-}
-
-impl fmt::Display for UtilError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.details)
-    }
-}
 
-impl Error for UtilError {
-    fn description(&self) -> &str {
-        &self.details
+    pub fn model(details: &str) -> Error {
+        Error::Model {
+            details: details.to_string(),
+        }
     }
-}
 
-impl From<IOError> for UtilError {
-    fn from(err: IOError) -> Self {
-        let desc = format!("io::Error: {}", err.to_string());
-        UtilError::new(&desc[..])
+    /// Attach path of the file the error occurred in, unless a path is already attached
+    ///
+    /// Only file related errors (`Io`, `ModelFormat`) hold a path, others are left unchanged.
+    pub fn in_file(mut self, file: &str) -> Error {
+        match &mut self {
+            Error::Io { path, .. } | Error::ModelFormat { path, .. } if path.is_none() => {
+                *path = Some(String::from(file));
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// Attach number (starting at one) of the model file line the error occurred in
+    ///
+    /// I/O errors are turned into `ModelFormat` errors to keep the line.
+    pub fn at_line(self, number: usize) -> Error {
+        match self {
+            Error::ModelFormat {
+                path,
+                details,
+                source,
+                ..
+            } => Error::ModelFormat {
+                path,
+                line: Some(number),
+                details,
+                source,
+            },
+            Error::Io { path, source } => Error::ModelFormat {
+                path,
+                line: Some(number),
+                details: source.to_string(),
+                source: Some(Box::new(source)),
+            },
+            err => err,
+        }
     }
-}
 
-impl From<std::boxed::Box<dyn std::error::Error>> for UtilError {
-    fn from(err: std::boxed::Box<dyn std::error::Error>) -> Self {
-        let desc = format!("{}", err.to_string());
-        UtilError::new(&desc[..])
+    /// Provide path of the file the error occurred in, if known
+    pub fn file(&self) -> Option<&str> {
+        match self {
+            Error::Io { path, .. } | Error::ModelFormat { path, .. } => {
+                path.as_ref().map(|path| &path[..])
+            }
+            _ => None,
+        }
     }
-}
-
-
-#[derive(Debug)]
-pub struct SmoothingError {
-    details: String,
-}
 
-impl SmoothingError {
-    pub fn new(msg: &str) -> SmoothingError {
-        SmoothingError {
-            details: msg.to_string(),
+    /// Provide number of the model file line the error occurred in, if known
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Error::ModelFormat { line, .. } => *line,
+            _ => None,
         }
     }
 }
 
-impl fmt::Display for SmoothingError {
+impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.details)
+        match self {
+            Error::Io { path, source } => match path {
+                Some(path) => write!(f, "{}: {}", path, source),
+                None => write!(f, "I/O error: {}", source),
+            },
+            Error::ModelFormat {
+                path,
+                line,
+                details,
+                ..
+            } => match (path, line) {
+                (Some(path), Some(line)) => write!(f, "{}:{}: {}", path, line, details),
+                (Some(path), None) => write!(f, "{}: {}", path, details),
+                (None, Some(line)) => write!(f, "line {}: {}", line, details),
+                (None, None) => write!(f, "{}", details),
+            },
+            Error::AlphabetMismatch {
+                model,
+                model_sigma,
+                sigma,
+            } => write!(
+                f,
+                "Model '{}' is based on alphabet {} but guessing uses {}",
+                model,
+                model_sigma.name(),
+                sigma.name()
+            ),
+            Error::MarkerMismatch { model } => write!(
+                f,
+                "Model '{}' differs from guess configuration in text marker setting",
+                model
+            ),
+            Error::NGramLengthMismatch {
+                model,
+                model_length,
+                ngram_length,
+            } => write!(
+                f,
+                "Model '{}' holds ngrams up to length {} but guessing uses length {}",
+                model, model_length, ngram_length
+            ),
+            Error::UnknownNGram { ngram } => write!(f, "Unknown ngram: {}", ngram),
+            Error::InvalidSymbol { bytes, source } => {
+                write!(f, "Symbol {:?} isn't valid utf-8: {}", bytes, source)
+            }
+            Error::Path { details, .. } => write!(f, "{}", details),
+            Error::Config { details } => write!(f, "{}", details),
+            Error::Model { details } => write!(f, "{}", details),
+            Error::Batch { failed, total } => {
+                write!(f, "{} of {} files couldn't be guessed", failed, total)
+            }
+        }
     }
 }
 
-impl Error for SmoothingError {
-    fn description(&self) -> &str {
-        &self.details
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::InvalidSymbol { source, .. } => Some(source),
+            Error::ModelFormat { source, .. } | Error::Path { source, .. } => source
+                .as_ref()
+                .map(|source| source.as_ref() as &(dyn StdError + 'static)),
+            _ => None,
+        }
     }
 }
 
-impl From<IOError> for SmoothingError {
-    fn from(err: IOError) -> Self {
-        let desc = format!("io::Error: {}", err.to_string());
-        SmoothingError::new(&desc[..])
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io {
+            path: None,
+            source: err,
+        }
     }
 }
 
-impl From<UtilError> for SmoothingError {
-    fn from(err: UtilError) -> Self {
-        let desc = format!("UtilError: {}", err.to_string());
-        SmoothingError::new(&desc[..])
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_error1() {
+        let err = Error::from(io::Error::new(io::ErrorKind::NotFound, "gone"));
+        assert!(err.source().is_some());
+        let err = err.in_file("english.model").in_file("german.model");
+        assert_eq!(Some("english.model"), err.file());
+        assert_eq!("english.model: gone", err.to_string());
+        let err = err.at_line(3);
+        assert_eq!(Some(3), err.line());
+        assert_eq!("english.model:3: gone", err.to_string());
+        assert!(err.source().is_some());
+        match Error::model_format("Illformed line").at_line(2) {
+            Error::ModelFormat { line: Some(2), .. } => {}
+            err => panic!("unexpected error: {:?}", err),
+        }
+        assert_eq!(None, Error::config("unknown").in_file("x").file());
     }
 }
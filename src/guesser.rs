@@ -0,0 +1,123 @@
+use errors::Error;
+use inferer::{Classification, Guess, Inferer, Thresholds};
+use models::probability_model::ProbabilityModel;
+use models::sigma::{Sigma, SigmaType};
+use models::text_model::TextModel;
+use scoring::ScoringType;
+use segmentation::Segment;
+use std::collections::HashMap;
+
+/// Guess the language of texts held in memory
+///
+/// Built from probability models held in memory, e.g. trained by `ModelTrainer` or read once at
+/// startup, so guessing doesn't touch the filesystem.
+///
+/// # Fields
+///
+/// * `inferer` - inferer holding the language models
+/// * `sigma` - alphabet of the texts, `Corpus` alphabets are extended by the models' symbols
+/// * `ngram_length` - length of ngrams the guessing is based on
+pub struct LanguageGuesser {
+    inferer: Inferer,
+    sigma: Sigma,
+    ngram_length: usize,
+}
+
+impl LanguageGuesser {
+    /// Init from probability models
+    ///
+    /// Models with a header are rejected if they were built for another alphabet, text marker
+    /// setting or a shorter ngram length.
+    pub fn new(
+        models: Vec<ProbabilityModel>,
+        sigma: Sigma,
+        ngram_length: usize,
+    ) -> Result<LanguageGuesser, Error> {
+        let inferer: Inferer = Inferer::from_models(models, &sigma, ngram_length, false)?;
+        let mut sigma: Sigma = sigma;
+        if sigma.sigma_type == SigmaType::Corpus {
+            sigma.sigma.extend(inferer.symbols());
+        }
+        Ok(LanguageGuesser {
+            inferer,
+            sigma,
+            ngram_length,
+        })
+    }
+
+    /// Set how ngrams are scored against the models, `Exact` by default
+    pub fn set_scoring_type(&mut self, scoring_type: ScoringType) {
+        self.inferer.set_scoring_type(scoring_type);
+    }
+
+    /// Set prior probabilities of the models by model name, uniform by default
+    pub fn set_priors(&mut self, priors: HashMap<String, f64>) {
+        self.inferer.set_priors(priors);
+    }
+
+    /// Set thresholds for rejecting the best ranked model when classifying
+    pub fn set_thresholds(&mut self, thresholds: Thresholds) {
+        self.inferer.set_thresholds(thresholds);
+    }
+
+    /// Guess the language of a text
+    ///
+    /// Guesses of all models are ranked by likelihood, most likely first.
+    pub fn guess(&self, text: &str) -> Result<Vec<Guess>, Error> {
+        self.inferer.infer(&self.text_model(text)?)
+    }
+
+    /// Guess the language of a text, keeping only the `k` best ranked guesses
+    pub fn guess_top(&self, text: &str, k: usize) -> Result<Vec<Guess>, Error> {
+        self.inferer.infer_top(&self.text_model(text)?, k)
+    }
+
+    /// Classify a text with the most likely language unless it is rejected by the thresholds
+    pub fn classify(&self, text: &str) -> Result<Classification, Error> {
+        self.inferer.classify(&self.text_model(text)?)
+    }
+
+    /// Split a text into segments of different language, see `Inferer::segment`
+    pub fn segment(&self, text: &str, switch_penalty: f64) -> Result<Vec<Segment>, Error> {
+        self.inferer
+            .segment(&self.text_model(text)?, switch_penalty)
+    }
+
+    fn text_model(&self, text: &str) -> Result<TextModel, Error> {
+        let mut text_model = TextModel::new(self.ngram_length, &self.sigma)?;
+        text_model.extend(text);
+        Ok(text_model)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use smoothing::SmoothingType;
+    use trainer::ModelTrainer;
+
+    #[test]
+    fn test_guesser1() {
+        let sigma: Sigma = Sigma::new(None, SigmaType::Test);
+        let train = |name: &str, corpus: &str| {
+            ModelTrainer::new(name, sigma.clone(), 2, SmoothingType::AddOneSmoothing)
+                .train(&[corpus])
+                .unwrap()
+        };
+        let models = vec![train("abby", "abababab"), train("cathy", "cacacaca")];
+        let guesser = LanguageGuesser::new(models, sigma.clone(), 2).unwrap();
+        let guesses = guesser.guess("abab").unwrap();
+        assert_eq!(2, guesses.len());
+        assert_eq!("abby", guesses[0].name);
+        assert_eq!("cathy", guesser.guess_top("acac", 1).unwrap()[0].name);
+        assert_eq!(1, guesser.guess_top("acac", 1).unwrap().len());
+        let segments = guesser.segment("ababcaca", 4.0).unwrap();
+        assert_eq!(
+            vec!["abby", "cathy"],
+            segments.iter().map(|s| &s.name[..]).collect::<Vec<&str>>()
+        );
+        // models of another alphabet are rejected
+        let other: Sigma = Sigma::new(None, SigmaType::AlphaNum);
+        assert!(LanguageGuesser::new(vec![train("abby", "abab")], other, 2).is_err());
+    }
+}
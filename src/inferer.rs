@@ -1,11 +1,97 @@
-use errors::InfererError;
+use errors::Error;
 use models::probability_model::ProbabilityModel;
+use models::sigma::Sigma;
+use models::symbol::Symbol;
 use models::text_model::TextModel;
-use std::sync::mpsc::{channel, Receiver, Sender};
-use std::sync::Arc;
+use segmentation::{merge_segments, viterbi, Segment};
+use scoring::{fit_lambdas, interpolated_probability, uniform_lambdas, BackoffWeights, ScoringType};
+use std::cmp;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::mpsc::channel;
 use std::thread;
-use utils::{get_model_paths, sort_by_second_element};
+use utils::{get_model_paths, top_k_indices};
 
+/// Language guess for a text
+///
+/// # Fields
+///
+/// * `name` - name of the language model
+/// * `log_probability` - log2 likelihood of the text's ngrams under the model
+/// * `normalized` - log2 likelihood per ngram, comparable across texts of different lengths
+/// * `posterior` - probability of the model given the text among all models (softmax over log
+///   likelihoods including priors)
+/// * `ngram_count` - count of the text's ngrams the guess is based on
+#[derive(Clone, Debug, PartialEq)]
+pub struct Guess {
+    pub name: String,
+    pub log_probability: f64,
+    pub normalized: f64,
+    pub posterior: f64,
+    pub ngram_count: usize,
+}
+
+/// Outcome of classifying a text
+///
+/// # Language
+///
+/// The best ranked language model, given the text passed all rejection thresholds.
+///
+/// # Unknown
+///
+/// Even the best ranked language model fits the text too badly, e.g. the text is of a language
+/// no model is present for.
+///
+/// # Undetermined
+///
+/// The text holds too few symbols of the alphabet or the best ranked language models are too
+/// close to decide between them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verdict {
+    Language(String),
+    Unknown,
+    Undetermined,
+}
+
+/// Format verdict as language name, `unknown` or `undetermined`
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Verdict::Language(name) => write!(f, "{}", name),
+            Verdict::Unknown => write!(f, "unknown"),
+            Verdict::Undetermined => write!(f, "undetermined"),
+        }
+    }
+}
+
+/// Thresholds for rejecting the best ranked language model, all disabled by default
+///
+/// # Fields
+///
+/// * `min_score` - min log2 likelihood per ngram of the best guess
+/// * `min_margin` - min difference of the log2 likelihoods plus log2 priors per ngram of the best
+///   guess and the runner-up
+/// * `min_symbols` - min count of text symbols contained in the alphabet
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Thresholds {
+    pub min_score: Option<f64>,
+    pub min_margin: Option<f64>,
+    pub min_symbols: usize,
+}
+
+/// Classification of a text
+///
+/// # Fields
+///
+/// * `verdict` - language of the text or the reason it is rejected
+/// * `guesses` - all guesses ranked by likelihood, most likely first
+/// * `symbol_count` - count of text symbols contained in the alphabet
+#[derive(Clone, Debug, PartialEq)]
+pub struct Classification {
+    pub verdict: Verdict,
+    pub guesses: Vec<Guess>,
+    pub symbol_count: usize,
+}
 
 /// Infer text language according to language models
 /// Inferring is done based on text's ngrams of specified `ngram_length`.  Inferring will be done in parallel for the present `models` if `in_parallel` is true.
@@ -15,100 +101,404 @@ use utils::{get_model_paths, sort_by_second_element};
 /// * `models` - List of language models
 /// * `ngram_length` - length of ngrams the inference is based on
 /// * `in_parallel` - parallel processing flag
+/// * `scoring_type` - how ngrams are scored against the models
+/// * `backoff_weights` - Katz backoff weights per model, only calculated for `Backoff` scoring
+/// * `lambdas` - interpolation weights per model, only set for `Interpolation` scoring
+/// * `priors` - prior probabilities of the models by name
+/// * `thresholds` - thresholds for rejecting the best ranked model when classifying
+/// * `skipped` - errors of model files left out when loading
 ///
 pub struct Inferer {
     models: Vec<ProbabilityModel>,
     ngram_length: usize,
     in_parallel: bool,
+    scoring_type: ScoringType,
+    backoff_weights: Vec<BackoffWeights>,
+    lambdas: Vec<Vec<f64>>,
+    priors: HashMap<String, f64>,
+    thresholds: Thresholds,
+    skipped: Vec<Error>,
 }
 
 impl Inferer {
-    /// Init from directory holding dumped probability models files
+    /// Init from directory holding dumped probability models files, see `from_model_files`
     pub fn from_models_dir(
         dir: &str,
+        sigma: &Sigma,
         ngram_length: usize,
         in_parallel: bool,
-    ) -> Result<Inferer, InfererError> {
+        skip_invalid: bool,
+    ) -> Result<Inferer, Error> {
         let model_paths = get_model_paths(dir)?;
-        Inferer::from_model_files(model_paths, ngram_length, in_parallel)
+        Inferer::from_model_files(model_paths, sigma, ngram_length, in_parallel, skip_invalid)
     }
 
     /// Init from a list of paths
     ///
-    /// Init by reading probability models from give paths
+    /// Init by reading probability models from give paths, see `from_models`. A model that can't
+    /// be read or doesn't fit is an error, unless `skip_invalid` is set. Then the model is left
+    /// out and its error is kept, see `skipped`.
     pub fn from_model_files(
         model_paths: Vec<String>,
+        sigma: &Sigma,
         ngram_length: usize,
         in_parallel: bool,
-    ) -> Result<Inferer, InfererError> {
-        let models = model_paths
-            .into_iter()
-            .map(|path| {
-                let model = match ProbabilityModel::from_file(&path[..]) {
-                    Ok(model) => model,
-                    Err(err) => panic!("{} for file {}!", err, &path[..]),
-                };
-                model
-            })
-            .collect::<Vec<ProbabilityModel>>();
+        skip_invalid: bool,
+    ) -> Result<Inferer, Error> {
+        let mut models: Vec<ProbabilityModel> = Vec::new();
+        let mut skipped: Vec<Error> = Vec::new();
+        for path in model_paths {
+            let loaded = ProbabilityModel::from_file(&path).and_then(|model| {
+                if let Some(header) = &model.header {
+                    header
+                        .check_compatibility(sigma, ngram_length)
+                        .map_err(|err| err.in_file(&path))?;
+                }
+                Ok(model)
+            });
+            match loaded {
+                Ok(model) => models.push(model),
+                Err(err) if skip_invalid => skipped.push(err),
+                Err(err) => return Err(err),
+            }
+        }
+        let mut inferer = Inferer::from_models(models, sigma, ngram_length, in_parallel)?;
+        inferer.skipped = skipped;
+        Ok(inferer)
+    }
+
+    /// Init from probability models held in memory
+    ///
+    /// Models with a header are rejected if they were built for another alphabet, text marker
+    /// setting or a shorter ngram length.
+    pub fn from_models(
+        models: Vec<ProbabilityModel>,
+        sigma: &Sigma,
+        ngram_length: usize,
+        in_parallel: bool,
+    ) -> Result<Inferer, Error> {
+        for model in models.iter() {
+            if let Some(header) = &model.header {
+                header.check_compatibility(sigma, ngram_length)?;
+            }
+        }
         Ok(Inferer {
             models,
             ngram_length,
             in_parallel,
+            scoring_type: ScoringType::Exact,
+            backoff_weights: Vec::new(),
+            lambdas: Vec::new(),
+            priors: HashMap::new(),
+            thresholds: Thresholds::default(),
+            skipped: Vec::new(),
         })
     }
 
+    /// Set how ngrams are scored against the models, `Exact` by default
+    ///
+    /// For `Backoff` scoring the backoff weights of all models are calculated once here, for
+    /// `Interpolation` scoring the interpolation weights of all models are resolved.
+    pub fn set_scoring_type(&mut self, scoring_type: ScoringType) {
+        self.backoff_weights = match scoring_type {
+            ScoringType::Backoff => self
+                .models
+                .iter()
+                .map(|model| BackoffWeights::from_model(model, self.ngram_length))
+                .collect(),
+            _ => Vec::new(),
+        };
+        self.lambdas = match &scoring_type {
+            ScoringType::Interpolation(lambdas) => self
+                .models
+                .iter()
+                .map(|model| {
+                    let header_lambdas = model
+                        .header
+                        .as_ref()
+                        .and_then(|header| header.interpolation_weights.as_ref());
+                    match lambdas.as_ref().or(header_lambdas) {
+                        Some(lambdas) => fit_lambdas(lambdas, self.ngram_length),
+                        None => uniform_lambdas(self.ngram_length),
+                    }
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        self.scoring_type = scoring_type;
+    }
+
+    /// Set prior probabilities of the models by model name, uniform by default
+    ///
+    /// Models without prior get the average prior of all models. Priors are normalized to sum up
+    /// to one when calculating posteriors.
+    pub fn set_priors(&mut self, priors: HashMap<String, f64>) {
+        self.priors = priors;
+    }
+
+    /// Set thresholds for rejecting the best ranked model when classifying
+    pub fn set_thresholds(&mut self, thresholds: Thresholds) {
+        self.thresholds = thresholds;
+    }
+
+    /// Provide errors of the model files left out when loading with `skip_invalid`
+    pub fn skipped(&self) -> &[Error] {
+        &self.skipped
+    }
+
+    /// Provide symbols known to any of the models
+    ///
+    /// This builds the `Corpus` sigma for guessing from the models' unigrams.
+    pub fn symbols(&self) -> Vec<Symbol> {
+        self.models
+            .iter()
+            .flat_map(|model| model.symbols())
+            .collect()
+    }
+
     /// Infer most likely language for given text
-    pub fn infer(self, unclassified: &TextModel) -> Result<Vec<(String, f64)>, InfererError> {
+    ///
+    /// Guesses are ranked by posterior, i.e. log likelihood plus log prior, most likely first.
+    pub fn infer(&self, unclassified: &TextModel) -> Result<Vec<Guess>, Error> {
+        self.infer_top(unclassified, self.models.len())
+    }
+
+    /// Infer the `k` most likely languages for given text
+    ///
+    /// Guesses are ranked like by `infer`, but only the `k` best ones are sorted and returned.
+    /// Posteriors are still taken over all models.
+    pub fn infer_top(&self, unclassified: &TextModel, k: usize) -> Result<Vec<Guess>, Error> {
         let ngrams: Vec<String> = unclassified.ngram_iter(self.ngram_length).collect::<Vec<String>>();
-        let mut prob_table: Vec<(String, f64)> = match self.in_parallel {
-            true => self.parallel_infer(ngrams)?,
+        let log_probabilities: Vec<f64> = match self.in_parallel {
+            true => self.parallel_infer(&ngrams)?,
             false => self.successive_infer(&ngrams)?,
         };
-        prob_table = sort_by_second_element(prob_table)?;
-        Ok(prob_table)
-    }
-
-    /// Calculate likelihood of being of a specific language in parallel
-    fn parallel_infer(self, ngrams: Vec<String>) -> Result<Vec<(String, f64)>, InfererError> {
-        let shared_ngrams = Arc::new(ngrams);
-        let (sender, receiver): (Sender<(String, f64)>, Receiver<(String, f64)>) = channel();
-        for model in self.models {
-            let sender_instance = sender.clone();
-            let name: String = model.name.clone();
-            let ngrams = Arc::clone(&shared_ngrams);
-            thread::spawn(move || {
-                let probability = match calculate_log_space_probability(&model, &ngrams) {
-                    Ok(probability) => probability,
-                    Err(err) => panic!("Thread can't calculate probability because of {}", err),
-                };
-                match sender_instance.send((name, probability)) {
-                    Ok(_) => drop(sender_instance),
-                    Err(err) => panic!("Thread couldn't send probability because of {}", err),
-                };
-            });
+        let log_priors: Vec<f64> = self.log_priors();
+        let posteriors: Vec<f64> = posteriors(&log_probabilities, &log_priors);
+        let scores: Vec<f64> = log_probabilities
+            .iter()
+            .zip(log_priors.iter())
+            .map(|(log_probability, log_prior)| log_probability + log_prior)
+            .collect();
+        let ngram_count: usize = ngrams.len();
+        Ok(top_k_indices(&scores, k)
+            .into_iter()
+            .map(|idx| Guess {
+                name: self.models[idx].name.clone(),
+                log_probability: log_probabilities[idx],
+                normalized: normalize(log_probabilities[idx], ngram_count),
+                posterior: posteriors[idx],
+                ngram_count,
+            })
+            .collect())
+    }
+
+    /// Classify text with the most likely language unless it is rejected by the thresholds
+    ///
+    /// The text is `Undetermined` if it holds no ngram or no symbol of the alphabet, less than
+    /// `min_symbols` symbols of the alphabet or the best guess leads by less than `min_margin`.
+    /// It is `Unknown` if the best guess scores below `min_score` or there is no model at all.
+    pub fn classify(&self, unclassified: &TextModel) -> Result<Classification, Error> {
+        self.classify_top(unclassified, self.models.len())
+    }
+
+    /// Classify text like `classify`, keeping only the `k` best ranked guesses
+    pub fn classify_top(
+        &self,
+        unclassified: &TextModel,
+        k: usize,
+    ) -> Result<Classification, Error> {
+        let symbol_count: usize = unclassified.symbol_count();
+        // the runner-up is needed for judging the margin
+        let mut guesses: Vec<Guess> = self.infer_top(unclassified, usize::max(k, 2))?;
+        let verdict: Verdict = judge(&guesses, symbol_count, &self.thresholds);
+        guesses.truncate(k);
+        Ok(Classification {
+            verdict,
+            guesses,
+            symbol_count,
+        })
+    }
+
+    /// Split text into segments of different language
+    ///
+    /// Each ngram of the text is scored against all models, a Viterbi pass finds the most
+    /// likely sequence of models where switching the model costs `switch_penalty` bits. Each
+    /// text symbol is labeled by the ngram ending at it, successive symbols with the same label
+    /// form a segment. Segments are given as byte offsets into the text.
+    pub fn segment(
+        &self,
+        unclassified: &TextModel,
+        switch_penalty: f64,
+    ) -> Result<Vec<Segment>, Error> {
+        let ngrams: Vec<String> = unclassified.ngram_iter(self.ngram_length).collect();
+        let mut emissions: Vec<Vec<f64>> = Vec::with_capacity(ngrams.len());
+        for ngram in ngrams.iter() {
+            let scores = (0..self.models.len())
+                .map(|idx| self.log_probability(idx, ngram))
+                .collect::<Result<Vec<f64>, Error>>()?;
+            emissions.push(scores);
         }
-        drop(sender);
-        let mut prob_table: Vec<(String, f64)> = Vec::new();
-        while let Ok(guess) = receiver.recv() {
-            prob_table.push(guess);
+        let path: Vec<usize> = viterbi(&emissions, switch_penalty);
+        if path.is_empty() {
+            return Ok(Vec::new());
         }
-        Ok(prob_table)
+        // the ngram ending at a symbol is offset by the confix in front of the text
+        let offset: usize = unclassified.confix_length() + 1;
+        let labels: Vec<usize> = (0..unclassified.symbol_count())
+            .map(|pos| {
+                let ngram_idx: usize = (pos + offset).saturating_sub(self.ngram_length);
+                path[usize::min(ngram_idx, path.len() - 1)]
+            })
+            .collect();
+        let names: Vec<String> = self.models.iter().map(|model| model.name.clone()).collect();
+        Ok(merge_segments(&labels, unclassified.symbol_spans(), &names))
     }
 
-    /// Calculate likelihood of being of a specific language in successively
-    fn successive_infer(&self, ngrams: &Vec<String>) -> Result<Vec<(String, f64)>, InfererError> {
-        self.models
+    /// Provide normalized log2 priors of the models in model order
+    fn log_priors(&self) -> Vec<f64> {
+        let default: f64 = match self.priors.is_empty() {
+            true => 1.0,
+            false => self.priors.values().sum::<f64>() / self.priors.len() as f64,
+        };
+        let priors: Vec<f64> = self
+            .models
             .iter()
-            .map(|model| {
-                let name = model.name.clone();
-                match calculate_log_space_probability(&model, &ngrams) {
-                    Ok(probability) => Ok((name, probability)),
-                    Err(err) => Err(err),
-                }
-            })
+            .map(|model| self.priors.get(&model.name).cloned().unwrap_or(default))
+            .collect();
+        let total: f64 = priors.iter().sum();
+        priors.iter().map(|prior| (prior / total).log2()).collect()
+    }
+
+    /// Calculate likelihood of being of a specific language in parallel, in model order
+    fn parallel_infer(&self, ngrams: &Vec<String>) -> Result<Vec<f64>, Error> {
+        let (sender, receiver) = channel::<(usize, Result<f64, Error>)>();
+        thread::scope(|scope| {
+            for idx in 0..self.models.len() {
+                let sender_instance = sender.clone();
+                scope.spawn(move || {
+                    let probability = self.score(idx, ngrams);
+                    match sender_instance.send((idx, probability)) {
+                        Ok(_) => drop(sender_instance),
+                        Err(err) => panic!("Thread couldn't send probability because of {}", err),
+                    };
+                });
+            }
+        });
+        drop(sender);
+        let mut probabilities: Vec<f64> = vec![0.0; self.models.len()];
+        for (idx, probability) in receiver.iter() {
+            probabilities[idx] = probability?;
+        }
+        Ok(probabilities)
+    }
+
+    /// Calculate likelihood of being of a specific language in successively, in model order
+    fn successive_infer(&self, ngrams: &Vec<String>) -> Result<Vec<f64>, Error> {
+        (0..self.models.len())
+            .map(|idx| self.score(idx, ngrams))
             .collect()
     }
+
+    /// Score ngrams against the model at position `idx` according to the scoring type
+    fn score(&self, idx: usize, ngrams: &Vec<String>) -> Result<f64, Error> {
+        match &self.scoring_type {
+            ScoringType::Exact => calculate_log_space_probability(&self.models[idx], ngrams),
+            _ => ngrams
+                .iter()
+                .map(|ngram| self.log_probability(idx, ngram))
+                .sum(),
+        }
+    }
+
+    /// Provide log2 probability of a single ngram under the model at position `idx`
+    fn log_probability(&self, idx: usize, ngram: &str) -> Result<f64, Error> {
+        let model: &ProbabilityModel = &self.models[idx];
+        match &self.scoring_type {
+            ScoringType::Exact => model
+                .get_log(ngram)
+                .ok_or_else(|| Error::UnknownNGram {
+                    ngram: String::from(ngram),
+                }),
+            ScoringType::Backoff => Ok(self.backoff_weights[idx].probability(model, ngram).log2()),
+            ScoringType::Interpolation(_) => {
+                Ok(interpolated_probability(model, &self.lambdas[idx], ngram).log2())
+            }
+        }
+    }
+}
+
+/// Calculate posterior probabilities from log2 likelihoods and log2 priors
+///
+/// The posteriors are the softmax (base 2) over the sums of log likelihood and log prior.
+/// The max sum is subtracted before exponentiation to avoid underflow. If no model gives the
+/// text a positive likelihood, the posteriors equal the priors.
+pub fn posteriors(log_probabilities: &[f64], log_priors: &[f64]) -> Vec<f64> {
+    let scores: Vec<f64> = log_probabilities
+        .iter()
+        .zip(log_priors.iter())
+        .map(|(log_probability, log_prior)| log_probability + log_prior)
+        .collect();
+    let max: f64 = scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if !max.is_finite() {
+        return log_priors.iter().map(|log_prior| log_prior.exp2()).collect();
+    }
+    let exponentiated: Vec<f64> = scores.iter().map(|score| (score - max).exp2()).collect();
+    let total: f64 = exponentiated.iter().sum();
+    exponentiated.iter().map(|value| value / total).collect()
+}
+
+/// Judge guesses according to rejection thresholds, see `Inferer::classify`
+///
+/// The best guess and the runner-up are the ones of highest posterior, like guesses are ranked
+/// by `Inferer::infer`. Their margin is the difference of their log2 posteriors per ngram, i.e.
+/// of their log likelihoods plus log priors, which equals the difference of the log likelihoods
+/// per ngram for uniform priors.
+///
+/// Text without any symbol of the alphabet is `Undetermined`, even if it holds ngrams of text
+/// markers only.
+pub fn judge(guesses: &[Guess], symbol_count: usize, thresholds: &Thresholds) -> Verdict {
+    if symbol_count == 0 || symbol_count < thresholds.min_symbols {
+        return Verdict::Undetermined;
+    }
+    let mut ranked: Vec<&Guess> = guesses.iter().collect();
+    // stable, so guesses of equal posterior keep their order
+    ranked.sort_by(|guess1, guess2| {
+        guess2
+            .posterior
+            .partial_cmp(&guess1.posterior)
+            .unwrap_or(cmp::Ordering::Equal)
+    });
+    let best: &Guess = match ranked.first() {
+        Some(best) => best,
+        None => return Verdict::Unknown,
+    };
+    // without any ngram all models score the same
+    if best.ngram_count == 0 {
+        return Verdict::Undetermined;
+    }
+    if let Some(min_score) = thresholds.min_score {
+        // NaN scores are rejected as well
+        if best.normalized.is_nan() || best.normalized < min_score {
+            return Verdict::Unknown;
+        }
+    }
+    if let (Some(min_margin), Some(runner_up)) = (thresholds.min_margin, ranked.get(1)) {
+        let margin: f64 =
+            (best.posterior.log2() - runner_up.posterior.log2()) / best.ngram_count as f64;
+        if margin < min_margin {
+            return Verdict::Undetermined;
+        }
+    }
+    Verdict::Language(best.name.clone())
+}
+
+/// Normalize log2 likelihood by the count of ngrams it is based on
+fn normalize(log_probability: f64, ngram_count: usize) -> f64 {
+    match ngram_count {
+        0 => 0.0,
+        _ => log_probability / ngram_count as f64,
+    }
 }
 
 /// Take product of ngrams occurence probabilities
@@ -120,19 +510,250 @@ impl Inferer {
 pub fn calculate_log_space_probability(
     model: &ProbabilityModel,
     ngrams: &Vec<String>,
-) -> Result<f64, InfererError> {
+) -> Result<f64, Error> {
     let default: f64 = 1.0;
     let mut product = default.log2();
     for ngram in ngrams.iter() {
-        let prob: f64 = match model.get(&ngram[..]) {
-            Some(prob) => *prob,
+        let log_prob: f64 = match model.get_log(&ngram[..]) {
+            Some(log_prob) => log_prob,
             None => {
-                // Todo possible backoff
-                // Shouldn't occur cause ngram models are total
-                return Err(InfererError::new(&format!("Unknown ngram: {}", ngram)[..]));
+                // Shouldn't occur cause unseen ngrams are covered by sparse models' unseen entries,
+                // otherwise use backoff or interpolation scoring
+                return Err(Error::UnknownNGram {
+                    ngram: ngram.clone(),
+                });
             }
         };
-        product += prob.log2();
+        product += log_prob;
     }
     Ok(product)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use models::sigma::SigmaType;
+    use smoothing::SmoothingType;
+    use std::fs;
+    use std::io;
+    use trainer::ModelTrainer;
+    use utils::{RecordReader, RecordSeparator};
+
+    #[test]
+    fn test_inferer1() {
+        let result = posteriors(&[-10.0, -11.0, -13.0], &[-1.0, -1.0, -1.0]);
+        assert!((result[0] - 8.0 / 13.0).abs() < 1e-12);
+        assert!((result[1] - 4.0 / 13.0).abs() < 1e-12);
+        assert!((result[2] - 1.0 / 13.0).abs() < 1e-12);
+        // likelihoods too small for f64 still give valid posteriors
+        let result = posteriors(&[-100000.0, -100001.0], &[0.5f64.log2(), 0.5f64.log2()]);
+        assert!((result[0] - 2.0 / 3.0).abs() < 1e-12);
+        // priors shift posteriors
+        let result = posteriors(&[-1.0, -1.0], &[0.75f64.log2(), 0.25f64.log2()]);
+        assert!((result[0] - 0.75).abs() < 1e-12);
+        let result = posteriors(&[f64::NEG_INFINITY], &[0.0]);
+        assert_eq!(vec![1.0], result);
+    }
+
+    #[test]
+    fn test_inferer2() {
+        let sigma: Sigma = Sigma::new(Some(35), SigmaType::Test);
+        let paths = vec![String::from("./data/models/test.model")];
+        let mut inferer = Inferer::from_model_files(paths, &sigma, 1, false, false).unwrap();
+        let mut text_model = TextModel::new(1, &sigma).unwrap();
+        text_model.extend("abc");
+        let guesses = inferer.infer(&text_model).unwrap();
+        assert_eq!(1, guesses.len());
+        assert_eq!("test", guesses[0].name);
+        assert!(inferer.skipped().is_empty());
+        let paths = vec![
+            String::from("./data/models/test.model"),
+            String::from("./data/models/missing.model"),
+        ];
+        assert!(Inferer::from_model_files(paths.clone(), &sigma, 1, false, false).is_err());
+        let skipping = Inferer::from_model_files(paths, &sigma, 1, false, true).unwrap();
+        assert_eq!(1, skipping.skipped().len());
+        assert_eq!(1, skipping.infer(&text_model).unwrap().len());
+        assert_eq!(1.0, guesses[0].posterior);
+        assert_eq!(guesses[0].ngram_count, text_model.ngram_iter(1).count());
+        assert_eq!(
+            guesses[0].log_probability / guesses[0].ngram_count as f64,
+            guesses[0].normalized
+        );
+        let mut priors: HashMap<String, f64> = HashMap::new();
+        priors.insert(String::from("test"), 0.2);
+        inferer.set_priors(priors);
+        assert_eq!(1.0, inferer.infer(&text_model).unwrap()[0].posterior);
+        let classification = inferer.classify(&text_model).unwrap();
+        assert_eq!(Verdict::Language(String::from("test")), classification.verdict);
+        assert_eq!(3, classification.symbol_count);
+        inferer.set_thresholds(Thresholds {
+            min_symbols: 4,
+            ..Thresholds::default()
+        });
+        assert_eq!(Verdict::Undetermined, inferer.classify(&text_model).unwrap().verdict);
+        let classification = inferer.classify_top(&text_model, 0).unwrap();
+        assert_eq!(Verdict::Undetermined, classification.verdict);
+        assert!(classification.guesses.is_empty());
+        assert_eq!(1, inferer.infer_top(&text_model, 3).unwrap().len());
+        let segments = inferer.segment(&text_model, 8.0).unwrap();
+        assert_eq!(
+            vec![Segment {
+                start: 0,
+                end: 3,
+                name: String::from("test"),
+            }],
+            segments
+        );
+    }
+
+    #[test]
+    fn test_inferer3() {
+        // posteriors of english and german for uniform priors
+        let uniform = posteriors(&[-30.0, -35.0], &[-1.0, -1.0]);
+        let guess = |name: &str, normalized: f64, posterior: f64| Guess {
+            name: String::from(name),
+            log_probability: normalized * 10.0,
+            normalized,
+            posterior,
+            ngram_count: 10,
+        };
+        let guesses = vec![
+            guess("english", -3.0, uniform[0]),
+            guess("german", -3.5, uniform[1]),
+        ];
+        let mut thresholds = Thresholds::default();
+        assert_eq!(
+            Verdict::Language(String::from("english")),
+            judge(&guesses, 10, &thresholds)
+        );
+        assert_eq!(Verdict::Unknown, judge(&[], 10, &thresholds));
+        thresholds.min_score = Some(-2.5);
+        assert_eq!(Verdict::Unknown, judge(&guesses, 10, &thresholds));
+        thresholds.min_score = Some(-3.0);
+        thresholds.min_margin = Some(1.0);
+        assert_eq!(Verdict::Undetermined, judge(&guesses, 10, &thresholds));
+        thresholds.min_margin = Some(0.5);
+        assert_eq!(
+            Verdict::Language(String::from("english")),
+            judge(&guesses, 10, &thresholds)
+        );
+        thresholds.min_symbols = 11;
+        assert_eq!(Verdict::Undetermined, judge(&guesses, 10, &thresholds));
+        let empty = Guess {
+            ngram_count: 0,
+            ..guess("english", 0.0, 1.0)
+        };
+        assert_eq!(Verdict::Undetermined, judge(&[empty], 0, &Thresholds::default()));
+        // markers of an empty text make up ngrams, but no symbol of the alphabet
+        let markers = Guess {
+            ngram_count: 4,
+            ..guess("english", -2.0, 1.0)
+        };
+        assert_eq!(Verdict::Undetermined, judge(&[markers], 0, &Thresholds::default()));
+        // a prior of 1 / 65 for english lets german win by 1 bit over 10 ngrams
+        let log_priors = [(1.0f64 / 65.0).log2(), (64.0f64 / 65.0).log2()];
+        let weighted = posteriors(&[-30.0, -35.0], &log_priors);
+        let guesses = vec![
+            guess("english", -3.0, weighted[0]),
+            guess("german", -3.5, weighted[1]),
+        ];
+        thresholds.min_symbols = 0;
+        thresholds.min_score = None;
+        thresholds.min_margin = Some(0.09);
+        assert_eq!(
+            Verdict::Language(String::from("german")),
+            judge(&guesses, 10, &thresholds)
+        );
+        thresholds.min_margin = Some(0.11);
+        assert_eq!(Verdict::Undetermined, judge(&guesses, 10, &thresholds));
+    }
+
+    #[test]
+    fn test_inferer4() {
+        let sigma: Sigma = Sigma::new(None, SigmaType::Test);
+        let mut paths: Vec<String> = Vec::new();
+        let dumps = [
+            ("x", "a\t0.6\nb\t0.3\nc\t0.1\n"),
+            ("y", "a\t0.4\nb\t0.5\nc\t0.1\n"),
+        ];
+        for (name, dump) in dumps.iter() {
+            let file_name = format!("naive_langguesser_prior_{}.model", name);
+            let path = std::env::temp_dir().join(file_name);
+            let path = path.to_str().unwrap().to_string();
+            fs::write(&path, dump).unwrap();
+            paths.push(path);
+        }
+        let mut inferer =
+            Inferer::from_model_files(paths.clone(), &sigma, 1, false, false).unwrap();
+        for path in paths.iter() {
+            fs::remove_file(path).unwrap();
+        }
+        let mut text_model = TextModel::new(1, &sigma).unwrap();
+        text_model.extend("a");
+        assert_eq!("naive_langguesser_prior_x", inferer.infer(&text_model).unwrap()[0].name);
+        // x leads by log2(0.6 / 0.4) bits, the prior of y outweighs it by log2(9) bits
+        let mut priors: HashMap<String, f64> = HashMap::new();
+        priors.insert(String::from("naive_langguesser_prior_x"), 0.1);
+        priors.insert(String::from("naive_langguesser_prior_y"), 0.9);
+        inferer.set_priors(priors);
+        let guesses = inferer.infer(&text_model).unwrap();
+        assert_eq!("naive_langguesser_prior_y", guesses[0].name);
+        assert!((guesses[0].posterior - 0.36 / 0.42).abs() < 1e-12);
+        let top = inferer.infer_top(&text_model, 1).unwrap();
+        assert_eq!(1, top.len());
+        assert_eq!("naive_langguesser_prior_y", top[0].name);
+        let classification = inferer.classify_top(&text_model, 1).unwrap();
+        assert_eq!(
+            Verdict::Language(String::from("naive_langguesser_prior_y")),
+            classification.verdict
+        );
+    }
+
+    #[test]
+    fn test_inferer5() {
+        let sigma: Sigma = Sigma::new(Some(35), SigmaType::Test);
+        let smoothing_type = SmoothingType::WittenBellSmoothing;
+        let model = ModelTrainer::new("test", sigma.clone(), 2, smoothing_type)
+            .train(&["aabcbaabaaac"])
+            .unwrap();
+        let inferer = Inferer::from_models(vec![model], &sigma, 2, false).unwrap();
+        for text in ["", "xyz"].iter() {
+            let mut text_model = TextModel::new(2, &sigma).unwrap();
+            text_model.extend(text);
+            let classification = inferer.classify(&text_model).unwrap();
+            assert_eq!(0, classification.symbol_count);
+            assert!(classification.guesses[0].ngram_count > 0);
+            assert_eq!(Verdict::Undetermined, classification.verdict, "text: '{}'", text);
+        }
+    }
+
+    #[test]
+    fn test_inferer6() {
+        let sigma: Sigma = Sigma::new(Some(35), SigmaType::Test);
+        let smoothing_type = SmoothingType::WittenBellSmoothing;
+        let model = ModelTrainer::new("test", sigma.clone(), 2, smoothing_type)
+            .train(&["aabcbaabaaac"])
+            .unwrap();
+        let inferer = Inferer::from_models(vec![model], &sigma, 2, false).unwrap();
+        let verdicts = |input: &[u8], separator: RecordSeparator| -> Vec<Verdict> {
+            RecordReader::new(io::Cursor::new(input.to_vec()), separator)
+                .map(|record| {
+                    let mut text_model = TextModel::new(2, &sigma).unwrap();
+                    text_model.extend(&record.unwrap());
+                    inferer.classify(&text_model).unwrap().verdict
+                })
+                .collect()
+        };
+        let test = Verdict::Language(String::from("test"));
+        // empty records and records without symbols of the alphabet hold marker ngrams only
+        assert_eq!(
+            vec![test.clone(), Verdict::Undetermined, Verdict::Undetermined, test.clone()],
+            verdicts(b"abc\n\nxyz\nba\n", RecordSeparator::Line)
+        );
+        assert_eq!(
+            vec![test.clone(), Verdict::Undetermined, test],
+            verdicts(b"abc\n\n!?\n\nba", RecordSeparator::Blank)
+        );
+    }
+}
@@ -1,23 +1,48 @@
 #[macro_use]
 extern crate lazy_static; //compile regex only once in loops
+extern crate glob;
 extern crate itertools;
+extern crate memmap;
 extern crate regex;
 
-use errors::GuessingError;
-use errors::ModellingError;
-use inferer::Inferer;
+use checker::check_model;
 use models::count_model::CountModel;
-use models::probability_model::ProbabilityModel;
-use models::text_model::TextModel;
+use output::{GuessResult, GuessWriter};
 use std::fs;
+use std::io;
+use std::path::Path;
+use utils::{
+    collect_text_paths, parallel_map, read_chunks, read_text, RecordReader, RecordSeparator,
+    CHUNK_SIZE,
+};
 
+mod checker;
 pub mod config;
 mod errors;
+mod guesser;
 mod inferer;
 mod models;
+pub mod output;
+mod scoring;
+mod segmentation;
 mod smoothing;
+mod trainer;
 mod utils;
 
+pub use checker::Issue;
+pub use errors::Error;
+pub use guesser::LanguageGuesser;
+pub use inferer::{Classification, Guess, Inferer, Thresholds, Verdict};
+pub use models::model_header::ModelHeader;
+pub use models::probability_model::{ModelFormat, ProbabilityModel, NORMALIZATION_TOLERANCE};
+pub use models::sigma::{Sigma, SigmaType};
+pub use models::text_model::TextModel;
+pub use output::OutputFormat;
+pub use scoring::ScoringType;
+pub use segmentation::Segment;
+pub use smoothing::SmoothingType;
+pub use trainer::{CorpusStats, ModelTrainer};
+
 /// Definition of execution modes of `naive_langguesser`
 ///
 /// # Model
@@ -27,44 +52,304 @@ mod utils;
 /// # Guess
 ///
 /// Classify a text with the most probable language based on present language models.
+///
+/// # Update
+///
+/// Add text to a language model based on the raw counts persisted next to it.
+///
+/// # Convert
+///
+/// Convert a language model between text and binary format.
+///
+/// # Check
+///
+/// Check language models for consistency, e.g. before releasing them.
 pub enum Mode {
     Model,
     Guess,
+    Update,
+    Convert,
+    Check,
+}
+
+/// Calculate a probability based language model from text example files, all counted together
+///
+/// # Arguments
+///
+/// * `config` - a struct holding config settings, partly given through cli
+pub fn model(config: config::ModelConfig) -> Result<(), Error> {
+    let paths: Vec<String> = collect_text_paths(&config.paths)?;
+    let mut trainer = ModelTrainer::new(
+        &config.modelname,
+        config.sigma.clone(),
+        config.ngram_length,
+        config.smoothing_type.clone(),
+    );
+    if let Some(held_out) = &config.held_out {
+        trainer.set_held_out(&read_text(held_out)?);
+    }
+    if config.sigma.sigma_type == SigmaType::Corpus {
+        // the alphabet has to be complete before counting, so corpus files are read twice
+        extend_sigma(&mut trainer, &paths)?;
+    }
+    add_corpus_files(&mut trainer, &paths)?;
+    if let Some(model_dir) = Path::new(&config.outpath).parent() {
+        fs::create_dir_all(model_dir)?;
+    }
+    write_model(trainer, &config.outpath)
 }
 
-/// Calculate a probability based language model from a text example file
+/// Add text to a language model based on the raw counts persisted next to it
 ///
 /// # Arguments
 ///
 /// * `config` - a struct holding config settings, partly given through cli
-pub fn model(config: config::ModelConfig) -> Result<(), ModellingError> {
-    let mut text_model = TextModel::new(config.ngram_length, &config.sigma)?;
-    let mut count_model = CountModel::from_sigma(&config.sigma, config.ngram_length)?;
-    let mut probability_model = ProbabilityModel::from_name(&config.modelname)?;
-    let raw_text: String = fs::read_to_string(&config.filename)?;
-    text_model.extend(&raw_text[..]);
-    count_model.count_ngrams_from_text_model(&text_model)?;
-    count_model.smooth(&config.smoothing_type)?;
-    probability_model.add_unigram_probabilities(&count_model)?;
-    probability_model.add_ngram_probabilities(&count_model)?;
-    probability_model.write_to_file(&config.outpath)?;
+pub fn update(config: config::UpdateConfig) -> Result<(), Error> {
+    let paths: Vec<String> = collect_text_paths(&config.paths)?;
+    let counts_path: String = counts_path(&config.model_path);
+    let (header, counts) = CountModel::read_counts(&counts_path)?;
+    let mut trainer = ModelTrainer::from_counts(header, counts)?;
+    if let Some(smoothing_type) = &config.smoothing_type {
+        trainer.set_smoothing_type(smoothing_type.clone());
+    }
+    // the header of the counts holds the tuned settings of the model, they're replaced if given
+    if let Some(held_out) = &config.held_out {
+        trainer.set_held_out(&read_text(held_out)?);
+    }
+    extend_sigma(&mut trainer, &paths)?;
+    add_corpus_files(&mut trainer, &paths)?;
+    write_model(trainer, &config.model_path)
+}
+
+/// Extend a `Corpus` alphabet of a trainer with the symbols of text files
+fn extend_sigma(trainer: &mut ModelTrainer, paths: &[String]) -> Result<(), Error> {
+    for path in paths {
+        let file = fs::File::open(path).map_err(|err| Error::from(err).in_file(path))?;
+        read_chunks(file, CHUNK_SIZE, |chunk| trainer.extend_sigma(chunk))
+            .map_err(|err| err.in_file(path))?;
+    }
+    Ok(())
+}
+
+/// Stream text files into a trainer, reporting the statistics of each file
+fn add_corpus_files(trainer: &mut ModelTrainer, paths: &[String]) -> Result<(), Error> {
+    let mut total = CorpusStats {
+        bytes: 0,
+        symbols: 0,
+        ngrams: 0,
+    };
+    for path in paths {
+        let file = fs::File::open(path).map_err(|err| Error::from(err).in_file(path))?;
+        let stats: CorpusStats = trainer
+            .add_reader(file, CHUNK_SIZE)
+            .map_err(|err| err.in_file(path))?;
+        println!(
+            "Counted {}: {} bytes, {} symbols, {} ngrams",
+            path, stats.bytes, stats.symbols, stats.ngrams
+        );
+        total.bytes += stats.bytes;
+        total.symbols += stats.symbols;
+        total.ngrams += stats.ngrams;
+    }
+    if paths.len() > 1 {
+        println!(
+            "Counted {} files: {} bytes, {} symbols, {} ngrams",
+            paths.len(),
+            total.bytes,
+            total.symbols,
+            total.ngrams
+        );
+    }
     Ok(())
 }
 
+/// Write the probability model of a trainer and its raw counts next to it
+fn write_model(mut trainer: ModelTrainer, path: &str) -> Result<(), Error> {
+    let probability_model: ProbabilityModel = trainer.finish()?;
+    trainer.write_counts(&counts_path(path))?;
+    probability_model.write_to_file(path)
+}
+
+/// Path of the raw counts persisted next to a model, e.g. `english.counts` for `english.model`
+fn counts_path(model_path: &str) -> String {
+    Path::new(model_path)
+        .with_extension("counts")
+        .to_string_lossy()
+        .into_owned()
+}
+
 /// Classify a text with the most probable language based on available language models
 ///
 /// # Arguments
 ///
 /// * `config` - a struct holding config settings, partly given through cli
-pub fn guess(config: config::GuessConfig) -> Result<(), GuessingError> {
-    let mut text_model = TextModel::new(config.ngram_length, &config.sigma)?;
-    let inferer: Inferer =
-        Inferer::from_models_dir(&config.model_dir, config.ngram_length, config.in_parallel)?;
-    let raw_unclassified = fs::read_to_string(&config.filename)?;
-    text_model.extend(&raw_unclassified[..]);
-    let prob_table = inferer.infer(&text_model)?;
-    for (name, prob) in prob_table {
-        println!("Guessing {} with : {}", name, prob);
+pub fn guess(config: config::GuessConfig) -> Result<(), Error> {
+    let mut inferer: Inferer = Inferer::from_models_dir(
+        &config.model_dir,
+        &config.sigma,
+        config.ngram_length,
+        config.in_parallel,
+        config.skip_invalid_models,
+    )?;
+    for err in inferer.skipped() {
+        eprintln!("Warning: skipped language model: {}", err);
+    }
+    inferer.set_scoring_type(config.scoring_type.clone());
+    inferer.set_priors(config.priors.clone());
+    inferer.set_thresholds(config.thresholds.clone());
+    let mut sigma: Sigma = config.sigma.clone();
+    if sigma.sigma_type == SigmaType::Corpus {
+        sigma.sigma.extend(inferer.symbols());
+    }
+    if let Some(separator) = &config.separator {
+        return guess_records(&inferer, &sigma, &config, separator);
+    }
+    let paths: Vec<String> = collect_text_paths(&config.paths)?;
+    // a single file keeps the detailed output, batches give a row per file
+    let is_batch: bool = paths != config.paths || paths.len() > 1;
+    let stdout = io::stdout();
+    let mut writer = GuessWriter::new(stdout.lock(), config.output_format.clone(), !is_batch);
+    writer.set_verbose(config.verbose);
+    writer.set_ranked(config.top_k.is_some());
+    if !is_batch {
+        let result: GuessResult = guess_file(&inferer, &sigma, &config, &paths[0])?;
+        writer.write(&paths[0], &result)?;
+        writer.finish()?;
+        return Ok(());
+    }
+    let results: Vec<Result<GuessResult, Error>> =
+        parallel_map(&paths, config.jobs, |path| {
+            guess_file(&inferer, &sigma, &config, path)
+        });
+    let mut failed: usize = 0;
+    for (path, result) in paths.iter().zip(results) {
+        match result {
+            Ok(result) => writer.write(path, &result)?,
+            Err(err) => {
+                eprintln!("Can't guess {}: {}", path, err);
+                failed += 1;
+            }
+        }
+    }
+    writer.finish()?;
+    match failed {
+        0 => Ok(()),
+        _ => Err(Error::Batch {
+            failed,
+            total: paths.len(),
+        }),
+    }
+}
+
+/// Guess the language of each record read from stdin, writing a row per record
+///
+/// Rows are flushed record by record, so results show up while stdin is still being written.
+fn guess_records(
+    inferer: &Inferer,
+    sigma: &Sigma,
+    config: &config::GuessConfig,
+    separator: &RecordSeparator,
+) -> Result<(), Error> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut writer = GuessWriter::new(stdout.lock(), config.output_format.clone(), false);
+    writer.set_ranked(config.top_k.is_some());
+    let records = RecordReader::new(stdin.lock(), separator.clone());
+    for (idx, record) in records.enumerate() {
+        let record: String = record?;
+        let label: String = (idx + 1).to_string();
+        writer.write(&label, &guess_text(inferer, sigma, config, &record)?)?;
+        writer.flush()?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Guess the language of a single file, see `guess_text`
+fn guess_file(
+    inferer: &Inferer,
+    sigma: &Sigma,
+    config: &config::GuessConfig,
+    path: &str,
+) -> Result<GuessResult, Error> {
+    let raw_unclassified = read_text(path)?;
+    guess_text(inferer, sigma, config, &raw_unclassified)
+}
+
+/// Guess the language of a text, either as a whole or per segment
+///
+/// # Arguments
+///
+/// * `inferer` - inferer holding the loaded language models
+/// * `sigma` - alphabet of the text
+/// * `config` - a struct holding config settings, partly given through cli
+/// * `raw_unclassified` - the text
+fn guess_text(
+    inferer: &Inferer,
+    sigma: &Sigma,
+    config: &config::GuessConfig,
+    raw_unclassified: &str,
+) -> Result<GuessResult, Error> {
+    let mut text_model = TextModel::new(config.ngram_length, sigma)?;
+    text_model.extend(raw_unclassified);
+    if config.segment {
+        let segments = inferer.segment(&text_model, config.switch_penalty)?;
+        return Ok(GuessResult::Segmented(segments));
     }
+    let classification = match config.top_k {
+        Some(top_k) => inferer.classify_top(&text_model, top_k)?,
+        None => inferer.classify(&text_model)?,
+    };
+    Ok(GuessResult::Classified(classification))
+}
+
+/// Convert a language model between text and binary format
+///
+/// # Arguments
+///
+/// * `config` - a struct holding config settings, partly given through cli
+pub fn convert(config: config::ConvertConfig) -> Result<(), Error> {
+    let probability_model = ProbabilityModel::from_file(&config.input)?;
+    probability_model.write_to_file_as(&config.output, &config.format)?;
     Ok(())
 }
+
+/// Check language models for consistency, printing the issues found
+///
+/// All models are checked, a model that can't be loaded is reported and counts as failed.
+/// Fails if any model failed, see `checker::check_model`.
+///
+/// # Arguments
+///
+/// * `config` - a struct holding config settings, partly given through cli
+pub fn check(config: config::CheckConfig) -> Result<(), Error> {
+    let mut failed: usize = 0;
+    for path in &config.model_paths {
+        let issues: Vec<Issue> = match check_model(path, config.tolerance) {
+            Ok(issues) => issues,
+            Err(err) => {
+                // errors of loading carry the path already
+                println!("{}", err);
+                failed += 1;
+                continue;
+            }
+        };
+        for issue in &issues {
+            println!("{}: {}", path, issue);
+        }
+        if issues.is_empty() {
+            println!("{}: ok", path);
+        } else {
+            println!("{}: {} issues found", path, issues.len());
+            failed += 1;
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(Error::model(&format!(
+            "{} of {} models failed the check",
+            failed,
+            config.model_paths.len()
+        ))),
+    }
+}
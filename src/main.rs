@@ -3,30 +3,66 @@ extern crate naive_langguesser;
 extern crate clap;
 
 use clap::App;
+use naive_langguesser::Error;
+use naive_langguesser::config::CheckConfig;
+use naive_langguesser::config::ConvertConfig;
 use naive_langguesser::config::GuessConfig;
 use naive_langguesser::config::ModelConfig;
+use naive_langguesser::config::UpdateConfig;
 use std::process;
 
 fn main() {
     let yaml = load_yaml!("../cli.yml");
     let matches = App::from_yaml(yaml).get_matches();
     if let Some(matches) = matches.subcommand_matches("model") {
-        let config = ModelConfig::new(matches);
+        let config = ModelConfig::new(matches).unwrap_or_else(|err| exit_with_config_error(&err));
         process::exit(match naive_langguesser::model(config) {
             Ok(_) => 0,
             Err(err) => {
-                eprintln!("Application error: {:?}", err);
+                eprintln!("Application error: {}", err);
                 1
             }
         });
     } else if let Some(matches) = matches.subcommand_matches("guess") {
-        let config = GuessConfig::new(matches);
+        let config = GuessConfig::new(matches).unwrap_or_else(|err| exit_with_config_error(&err));
         process::exit(match naive_langguesser::guess(config) {
             Ok(_) => 0,
             Err(err) => {
-                eprintln!("Application error: {:?}", err);
+                eprintln!("Application error: {}", err);
+                1
+            }
+        });
+    } else if let Some(matches) = matches.subcommand_matches("update") {
+        let config = UpdateConfig::new(matches).unwrap_or_else(|err| exit_with_config_error(&err));
+        process::exit(match naive_langguesser::update(config) {
+            Ok(_) => 0,
+            Err(err) => {
+                eprintln!("Application error: {}", err);
+                1
+            }
+        });
+    } else if let Some(matches) = matches.subcommand_matches("convert") {
+        let config = ConvertConfig::new(matches).unwrap_or_else(|err| exit_with_config_error(&err));
+        process::exit(match naive_langguesser::convert(config) {
+            Ok(_) => 0,
+            Err(err) => {
+                eprintln!("Application error: {}", err);
+                1
+            }
+        });
+    } else if let Some(matches) = matches.subcommand_matches("check") {
+        let config = CheckConfig::new(matches).unwrap_or_else(|err| exit_with_config_error(&err));
+        process::exit(match naive_langguesser::check(config) {
+            Ok(_) => 0,
+            Err(err) => {
+                eprintln!("Application error: {}", err);
                 1
             }
         });
     };
 }
+
+fn exit_with_config_error(err: &Error) -> ! {
+    eprintln!("Configuration error: {}", err);
+    process::exit(2);
+}
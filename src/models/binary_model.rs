@@ -0,0 +1,240 @@
+use memmap::Mmap;
+use errors::Error;
+use models::model_header::{ModelHeader, HEADER_MAGIC};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::convert::TryInto;
+use std::fs;
+use std::io::prelude::*;
+
+/// First bytes of every binary model file
+pub const BINARY_MAGIC: &[u8; 8] = b"NLGBIN01";
+
+/// Check if the file at `path` starts with `BINARY_MAGIC`
+pub fn is_binary_model(path: &str) -> Result<bool, Error> {
+    let mut f = fs::File::open(path)?;
+    let mut magic: [u8; 8] = [0; 8];
+    match f.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == BINARY_MAGIC),
+        // files shorter than the magic can't be binary models
+        Err(_) => Ok(false),
+    }
+}
+
+/// Probability model table read through a memory map
+///
+/// The binary model file is laid out as little-endian sections, each aligned to 8 bytes:
+///
+/// 1. `BINARY_MAGIC`
+/// 2. `u64` length of the model header followed by the header as in text model files
+/// 3. `u64` count of ngrams `n` and `u64` length of the ngram blob
+/// 4. `n + 1` `u64` offsets of the ngrams into the ngram blob
+/// 5. ngram blob holding the utf-8 encoded ngrams in ascending byte order
+/// 6. `n` `f64` log2 probabilities in order of the ngrams
+///
+/// Lookups are done by binary search on the ngrams, so no parsing happens while loading.
+///
+/// # Fields
+///
+/// * `map` - memory mapped model file
+/// * `count` - count of ngrams in table
+/// * `offsets_start` - position of ngram offsets section
+/// * `keys_start` - position of ngram blob
+/// * `values_start` - position of log2 probabilities section
+pub struct MappedTable {
+    map: Mmap,
+    count: usize,
+    offsets_start: usize,
+    keys_start: usize,
+    values_start: usize,
+}
+
+impl MappedTable {
+    /// Map binary model file and locate its sections
+    pub fn open(path: &str) -> Result<(Option<ModelHeader>, MappedTable), Error> {
+        let f = fs::File::open(path)?;
+        // Safety: the model file is only read and expected to be left unchanged while mapped
+        let map = unsafe { Mmap::map(&f)? };
+        if map.len() < 16 || &map[..8] != BINARY_MAGIC {
+            return Err(Error::model_format("Not a binary model file"));
+        }
+        let header_len = read_u64(&map, 8)? as usize;
+        let header_end = 16 + header_len;
+        let header_bytes = map
+            .get(16..header_end)
+            .ok_or_else(|| Error::model_format("Truncated model header"))?;
+        let header = parse_header(header_bytes)?;
+        let count_pos = align(header_end);
+        let count = read_u64(&map, count_pos)? as usize;
+        let keys_len = read_u64(&map, count_pos + 8)? as usize;
+        if count > map.len() / 8 || keys_len > map.len() {
+            return Err(Error::model_format("Truncated binary model file"));
+        }
+        let offsets_start = count_pos + 16;
+        let keys_start = offsets_start + 8 * (count + 1);
+        let values_start = align(keys_start + keys_len);
+        if map.len() < values_start + 8 * count {
+            return Err(Error::model_format("Truncated binary model file"));
+        }
+        let table = MappedTable {
+            map,
+            count,
+            offsets_start,
+            keys_start,
+            values_start,
+        };
+        if table.offset(count) != keys_len {
+            return Err(Error::model_format("Inconsistent ngram offsets"));
+        }
+        Ok((header, table))
+    }
+
+    /// Provide count of ngrams in table
+    pub fn ngram_count(&self) -> usize {
+        self.count
+    }
+
+    /// Provide ngram at position `idx` of table
+    pub fn key(&self, idx: usize) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.key_bytes(idx))
+    }
+
+    /// Provide log2 probability at position `idx` of table
+    pub fn log_probability(&self, idx: usize) -> f64 {
+        let pos = self.values_start + 8 * idx;
+        f64::from_bits(u64::from_le_bytes(
+            self.map[pos..pos + 8].try_into().expect("slice of 8 bytes"),
+        ))
+    }
+
+    /// Look up log2 probability of an ngram
+    pub fn get_log(&self, ngram: &str) -> Option<f64> {
+        let needle: &[u8] = ngram.as_bytes();
+        let mut low: usize = 0;
+        let mut high: usize = self.count;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.key_bytes(mid).cmp(needle) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Some(self.log_probability(mid)),
+            }
+        }
+        None
+    }
+
+    fn offset(&self, idx: usize) -> usize {
+        let pos = self.offsets_start + 8 * idx;
+        u64::from_le_bytes(self.map[pos..pos + 8].try_into().expect("slice of 8 bytes")) as usize
+    }
+
+    fn key_bytes(&self, idx: usize) -> &[u8] {
+        let start = self.keys_start + self.offset(idx);
+        let end = self.keys_start + self.offset(idx + 1);
+        self.map.get(start..end).unwrap_or(&[])
+    }
+}
+
+/// Write ngrams with their log2 probabilities as binary model file
+pub fn write_binary_model(
+    path: &str,
+    header: Option<&ModelHeader>,
+    mut entries: Vec<(String, f64)>,
+) -> Result<(), Error> {
+    entries.sort_by(|lhs, rhs| lhs.0.as_bytes().cmp(rhs.0.as_bytes()));
+    let header: String = match header {
+        Some(header) => header.to_string(),
+        None => String::new(),
+    };
+    let mut buf: Vec<u8> = Vec::new();
+    buf.extend_from_slice(BINARY_MAGIC);
+    buf.extend_from_slice(&(header.len() as u64).to_le_bytes());
+    buf.extend_from_slice(header.as_bytes());
+    pad(&mut buf);
+    let keys_len: usize = entries.iter().map(|(ngram, _)| ngram.len()).sum();
+    buf.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    buf.extend_from_slice(&(keys_len as u64).to_le_bytes());
+    let mut offset: u64 = 0;
+    buf.extend_from_slice(&offset.to_le_bytes());
+    for (ngram, _) in entries.iter() {
+        offset += ngram.len() as u64;
+        buf.extend_from_slice(&offset.to_le_bytes());
+    }
+    for (ngram, _) in entries.iter() {
+        buf.extend_from_slice(ngram.as_bytes());
+    }
+    pad(&mut buf);
+    for (_, log_probability) in entries.iter() {
+        buf.extend_from_slice(&log_probability.to_bits().to_le_bytes());
+    }
+    fs::write(path, &buf).map_err(|err| Error::from(err).in_file(path))?;
+    Ok(())
+}
+
+fn parse_header(bytes: &[u8]) -> Result<Option<ModelHeader>, Error> {
+    if bytes.is_empty() {
+        return Ok(None);
+    }
+    let text = std::str::from_utf8(bytes)
+        .map_err(|_| Error::model_format("Model header isn't valid utf-8"))?;
+    let mut lines = text.lines();
+    if lines.next() != Some(HEADER_MAGIC) {
+        return Err(Error::model_format("Illformed model header"));
+    }
+    let header_lines: Vec<String> = lines
+        .take_while(|line| !line.is_empty())
+        .map(String::from)
+        .collect();
+    Ok(Some(ModelHeader::from_lines(&header_lines)?))
+}
+
+fn read_u64(map: &[u8], pos: usize) -> Result<u64, Error> {
+    match map.get(pos..pos + 8) {
+        Some(bytes) => Ok(u64::from_le_bytes(bytes.try_into().expect("slice of 8 bytes"))),
+        None => Err(Error::model_format("Truncated binary model file")),
+    }
+}
+
+fn align(pos: usize) -> usize {
+    (pos + 7) & !7
+}
+
+fn pad(buf: &mut Vec<u8>) {
+    let aligned = align(buf.len());
+    buf.resize(aligned, 0);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_binary_model1() {
+        let entries: Vec<(String, f64)> = vec![
+            (String::from("b"), -2.0),
+            (String::from("ζa"), -1.5),
+            (String::from("a"), -1.0),
+            (String::from("#"), f64::NEG_INFINITY),
+        ];
+        let path = std::env::temp_dir().join("naive_langguesser_binary_test.model");
+        let path = path.to_str().unwrap();
+        write_binary_model(path, None, entries).unwrap();
+        assert!(is_binary_model(path).unwrap());
+        let (header, table) = MappedTable::open(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(None, header);
+        assert_eq!(4, table.ngram_count());
+        assert_eq!("#", table.key(0));
+        assert_eq!(Some(-1.0), table.get_log("a"));
+        assert_eq!(Some(-2.0), table.get_log("b"));
+        assert_eq!(Some(-1.5), table.get_log("ζa"));
+        assert_eq!(Some(f64::NEG_INFINITY), table.get_log("#"));
+        assert_eq!(None, table.get_log("c"));
+    }
+
+    #[test]
+    fn test_binary_model2() {
+        assert!(!is_binary_model("./data/models/test.model").unwrap());
+        assert!(MappedTable::open("./data/models/test.model").is_err());
+    }
+}
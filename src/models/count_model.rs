@@ -1,9 +1,11 @@
-use models::errors::CountModelError;
+use errors::Error;
+use models::model_header::ModelHeader;
 use models::ngram_model::NGramModel;
-use models::sigma::{Sigma, NGramExt};
+use models::probability_model::read_text_dump;
+use models::sigma::{NGramExt, Sigma};
 use models::text_model::TextModel;
-use smoothing::{smoothing, SmoothingType};
 use std::collections::HashMap;
+use std::fs;
 
 /// Hold ngram occurence models of various length
 pub struct CountModel {
@@ -12,11 +14,12 @@ pub struct CountModel {
 }
 
 impl CountModel {
-    /// Init total `CountModel` from sigma (alphabet) and a max ngram length
+    /// Init `CountModel` from sigma (alphabet) and a max ngram length
     ///
-    /// A `CountModel` is initialised for (0..=max_ngram_length) each from a total permutation of
-    /// ngrams of the related length. The total permutation of ngrams of a certain length is build
-    /// from sigma.
+    /// A `CountModel` is initialised for (0..=max_ngram_length) each with an ngram model. The
+    /// unigram model is total, it is initialised with all symbols of sigma. Ngram models of
+    /// greater length are sparse, they only store ngrams seen in the text. Their vocabulary
+    /// size is the count of all permutations of ngrams of the related length build from sigma.
     ///
     /// # Arguments
     ///
@@ -25,11 +28,19 @@ impl CountModel {
     pub fn from_sigma(
         sigma: &Sigma,
         max_ngram_length: usize,
-    ) -> Result<CountModel, CountModelError> {
+    ) -> Result<CountModel, Error> {
         let mut ngram_models = HashMap::new();
+        let sigma_size: usize = sigma.as_ref().len();
         for ngram_length in 1..=max_ngram_length {
-            let ngrams: Vec<String> = sigma.ngrams(ngram_length);
-            let ngram_model = NGramModel::from_ngrams(&ngrams)?;
+            let ngram_model = match ngram_length {
+                1 => NGramModel::from_ngrams(&sigma.ngrams(ngram_length)?)?,
+                _ => {
+                    let vocabulary_size: usize = sigma_size
+                        .checked_pow(ngram_length as u32)
+                        .unwrap_or(usize::MAX);
+                    NGramModel::from_vocabulary_size(vocabulary_size)
+                }
+            };
             ngram_models.insert(ngram_length, ngram_model);
         }
         Ok(CountModel {
@@ -38,6 +49,11 @@ impl CountModel {
         })
     }
 
+    /// Provide max length of the counted ngrams
+    pub fn max_ngram_length(&self) -> usize {
+        self.max_ngram_length
+    }
+
     pub fn get_mut_ngram_model(&mut self, ngram_length: usize) -> Option<&mut NGramModel> {
         self.ngram_models.get_mut(&ngram_length)
     }
@@ -49,7 +65,7 @@ impl CountModel {
     pub fn count_ngrams_from_text_model(
         &mut self,
         text_model: &TextModel,
-    ) -> Result<(), CountModelError> {
+    ) -> Result<(), Error> {
         for idx in 1..=self.max_ngram_length {
             let ngrams: Vec<String> = text_model.ngram_iter(idx).collect::<Vec<String>>();
             self.count_ngrams(&ngrams, idx)?;
@@ -57,15 +73,32 @@ impl CountModel {
         Ok(())
     }
 
+    /// Count a single occurence of an ngram, e.g. streamed by `TextStream`
+    pub fn count_ngram(&mut self, ngram: &str) -> Result<(), Error> {
+        self.add_count(ngram, 1.0)
+    }
+
+    /// Add occurences of an ngram, e.g. counts persisted by a former training
+    pub fn add_count(&mut self, ngram: &str, occurences: f64) -> Result<(), Error> {
+        let ngram_length: usize = ngram.chars().count();
+        match self.get_mut_ngram_model(ngram_length) {
+            Some(model) => model.add_count(ngram, occurences),
+            None => Err(Error::model(&format!(
+                "Can't find count model for index: {}",
+                ngram_length
+            ))),
+        }
+    }
+
     fn count_ngrams(
         &mut self,
         ngrams: &Vec<String>,
         ngram_length: usize,
-    ) -> Result<(), CountModelError> {
+    ) -> Result<(), Error> {
         let ngram_model: &mut NGramModel = match self.get_mut_ngram_model(ngram_length) {
             Some(model) => model,
             None => {
-                return Err(CountModelError::new(
+                return Err(Error::model(
                     &format!("Can't find count model for index: {}", ngram_length)[..],
                 ))
             }
@@ -74,26 +107,33 @@ impl CountModel {
         Ok(())
     }
 
-    /// Smooth collected ngram counts
-    ///
-    /// Smoothing performs an redistribution of ngram counts to fill unseen ngrams.
+    /// Write raw (unsmoothed) counts of all ngram lengths as text dump
     ///
-    /// # Arguments
-    ///
-    /// * `smoothing_type` - smoothing_type to be performed on counts
-    pub fn smooth(&mut self, smoothing_type: &SmoothingType) -> Result<(), CountModelError> {
+    /// The dump looks like a text dump of a probability model, a header followed by lines like
+    /// `abc\t3`, but holds counts instead of probabilities. All unigrams are written, including
+    /// unseen ones, so the alphabet can be restored from the dump.
+    pub fn write_to_file(&self, path: &str, header: &ModelHeader) -> Result<(), Error> {
+        let mut write_buf: String = header.to_string();
         for idx in 1..=self.max_ngram_length {
-            let ngram_model: &mut NGramModel = match self.get_mut_ngram_model(idx) {
-                Some(model) => model,
-                None => {
-                    return Err(CountModelError::new(
-                        &format!("Can't find count model for index: {}", idx)[..],
-                    ))
+            if let Some(ngram_model) = self.get_ngram_model(idx) {
+                for (ngram, count) in ngram_model.iter() {
+                    write_buf.push_str(&format!("{}\t{}\n", ngram, count));
                 }
-            };
-            smoothing(ngram_model, &smoothing_type)?;
+            }
+        }
+        fs::write(path, &write_buf).map_err(|err| Error::from(err).in_file(path))
+    }
+
+    /// Read raw counts written by `write_to_file`
+    ///
+    /// The counts are returned as read, as the alphabet of a `CountModel` might still be extended
+    /// before the counts are added.
+    pub fn read_counts(path: &str) -> Result<(ModelHeader, Vec<(String, f64)>), Error> {
+        let (header, counts) = read_text_dump(path).map_err(|err| err.in_file(path))?;
+        match header {
+            Some(header) => Ok((header, counts)),
+            None => Err(Error::model_format("Count dump misses its header").in_file(path)),
         }
-        Ok(())
     }
 
     /// Provide tuple iterator for count models
@@ -101,7 +141,7 @@ impl CountModel {
     /// Provide iterator about two count models of successive ngram length
     ///
     /// Example: (1-gram-counts, 2-grams-counts) or (2-grams-counts, 3-grams-counts)
-    pub fn iter_tuple(&self) -> CountModelTupleIter {
+    pub fn iter_tuple(&self) -> CountModelTupleIter<'_> {
         CountModelTupleIter {
             model: self,
             idx: 0,
@@ -1,5 +1,6 @@
+pub mod binary_model;
 pub mod count_model;
-pub mod errors;
+pub mod model_header;
 pub mod ngram_model;
 pub mod probability_model;
 pub mod sigma;
@@ -0,0 +1,300 @@
+use errors::Error;
+use models::sigma::{Sigma, SigmaType};
+use smoothing::SmoothingType;
+use std::fmt;
+
+/// First line of every model file carrying a header
+pub const HEADER_MAGIC: &str = "#!naive_langguesser model";
+
+/// Version of the model file format written by this build
+pub const MODEL_FORMAT_VERSION: usize = 1;
+
+/// Settings a probability model was built with
+///
+/// The header is written in front of the ngram probabilities of a model file. It starts with
+/// `HEADER_MAGIC`, holds one `key: value` pair per line and is terminated by an empty line.
+/// As ngram lines always contain a tab, the header can't be confused with ngram lines.
+///
+/// # Fields
+///
+/// * `version` - model file format version
+/// * `name` - probability model name (related to modeled language)
+/// * `sigma_type` - alphabet the model is based on
+/// * `ngram_length` - max ngram length of the model
+/// * `set_marker` - text begin/end marker byte, if set
+/// * `smoothing_type` - smoothing applied to the ngram counts, pseudo-counts of lidstone
+///   smoothing are stored separately as `pseudo_counts`
+/// * `corpus_size` - size of the training text in bytes
+/// * `symbol_count` - count of training text symbols contained in sigma
+/// * `sigma_size` - count of distinct symbols in sigma (marker included)
+/// * `interpolation_weights` - weights of ngram lengths for interpolated scoring (shortest first)
+///   if estimated on held-out text
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelHeader {
+    pub version: usize,
+    pub name: String,
+    pub sigma_type: SigmaType,
+    pub ngram_length: usize,
+    pub set_marker: Option<u8>,
+    pub smoothing_type: SmoothingType,
+    pub corpus_size: usize,
+    pub symbol_count: usize,
+    pub sigma_size: usize,
+    pub interpolation_weights: Option<Vec<f64>>,
+}
+
+impl ModelHeader {
+    /// Init header for a model to be build, corpus statistics start at zero
+    pub fn new(
+        name: &str,
+        sigma: &Sigma,
+        ngram_length: usize,
+        smoothing_type: &SmoothingType,
+    ) -> ModelHeader {
+        ModelHeader {
+            version: MODEL_FORMAT_VERSION,
+            name: String::from(name),
+            sigma_type: sigma.sigma_type.clone(),
+            ngram_length,
+            set_marker: sigma.set_marker.as_ref().map(|symbol| symbol.symbol[0]),
+            smoothing_type: smoothing_type.clone(),
+            corpus_size: 0,
+            symbol_count: 0,
+            sigma_size: sigma.sigma.len(),
+            interpolation_weights: None,
+        }
+    }
+
+    /// Parse header from the `key: value` lines following `HEADER_MAGIC`
+    ///
+    /// Errors of ill-formed lines carry the line number in the dump, counting `HEADER_MAGIC` as
+    /// line one.
+    pub fn from_lines(lines: &[String]) -> Result<ModelHeader, Error> {
+        let mut version: Option<usize> = None;
+        let mut name: Option<String> = None;
+        let mut sigma_type: Option<SigmaType> = None;
+        let mut ngram_length: Option<usize> = None;
+        let mut set_marker: Option<Option<u8>> = None;
+        let mut smoothing_type: Option<SmoothingType> = None;
+        let mut corpus_size: usize = 0;
+        let mut symbol_count: usize = 0;
+        let mut sigma_size: usize = 0;
+        let mut interpolation_weights: Option<Vec<f64>> = None;
+        let mut pseudo_counts: Option<Vec<f64>> = None;
+        for (idx, line) in lines.iter().enumerate() {
+            let at_line = |err: Error| err.at_line(idx + 2);
+            let mut split = line.splitn(2, ": ");
+            let key = split.next().unwrap_or("");
+            let value = match split.next() {
+                Some(value) => value,
+                None => {
+                    return Err(at_line(Error::model_format(&format!(
+                        "Illformed header line: {}",
+                        line
+                    ))))
+                }
+            };
+            match key {
+                "version" => version = Some(parse_number(key, value).map_err(at_line)?),
+                "name" => name = Some(String::from(value)),
+                "alphabet" => {
+                    sigma_type = Some(SigmaType::from_name(value).ok_or_else(|| {
+                        at_line(Error::model_format(&format!("Unknown alphabet: {}", value)))
+                    })?)
+                }
+                "ngram_length" => ngram_length = Some(parse_number(key, value).map_err(at_line)?),
+                "marker" => {
+                    set_marker = match value {
+                        "none" => Some(None),
+                        _ => Some(Some(parse_number(key, value).map_err(at_line)? as u8)),
+                    }
+                }
+                "smoothing" => {
+                    smoothing_type = Some(SmoothingType::from_name(value).ok_or_else(|| {
+                        at_line(Error::model_format(&format!(
+                            "Unknown smoothing type: {}",
+                            value
+                        )))
+                    })?)
+                }
+                "corpus_size" => corpus_size = parse_number(key, value).map_err(at_line)?,
+                "symbol_count" => symbol_count = parse_number(key, value).map_err(at_line)?,
+                "sigma_size" => sigma_size = parse_number(key, value).map_err(at_line)?,
+                "interpolation_weights" => {
+                    interpolation_weights = Some(parse_weights(key, value).map_err(at_line)?)
+                }
+                "pseudo_counts" => pseudo_counts = Some(parse_weights(key, value).map_err(at_line)?),
+                // keys of later format versions are ignored
+                _ => (),
+            }
+        }
+        let version = version.ok_or_else(|| missing_key("version"))?;
+        if version > MODEL_FORMAT_VERSION {
+            return Err(Error::model_format(&format!(
+                "Unsupported model format version: {}",
+                version
+            )));
+        }
+        let smoothing_type = match smoothing_type.ok_or_else(|| missing_key("smoothing"))? {
+            SmoothingType::LidstoneSmoothing(_) => SmoothingType::LidstoneSmoothing(pseudo_counts),
+            smoothing_type => smoothing_type,
+        };
+        Ok(ModelHeader {
+            version,
+            name: name.ok_or_else(|| missing_key("name"))?,
+            sigma_type: sigma_type.ok_or_else(|| missing_key("alphabet"))?,
+            ngram_length: ngram_length.ok_or_else(|| missing_key("ngram_length"))?,
+            set_marker: set_marker.ok_or_else(|| missing_key("marker"))?,
+            smoothing_type,
+            corpus_size,
+            symbol_count,
+            sigma_size,
+            interpolation_weights,
+        })
+    }
+
+    /// Check if the model fits the alphabet and ngram length used for guessing
+    ///
+    /// A model holds ngrams of length `1..=ngram_length`, so guessing with shorter ngrams is fine.
+    pub fn check_compatibility(
+        &self,
+        sigma: &Sigma,
+        ngram_length: usize,
+    ) -> Result<(), Error> {
+        if self.sigma_type != sigma.sigma_type {
+            return Err(Error::AlphabetMismatch {
+                model: self.name.clone(),
+                model_sigma: self.sigma_type.clone(),
+                sigma: sigma.sigma_type.clone(),
+            });
+        }
+        let marker: Option<u8> = sigma.set_marker.as_ref().map(|symbol| symbol.symbol[0]);
+        if self.set_marker != marker {
+            return Err(Error::MarkerMismatch {
+                model: self.name.clone(),
+            });
+        }
+        if self.ngram_length < ngram_length {
+            return Err(Error::NGramLengthMismatch {
+                model: self.name.clone(),
+                model_length: self.ngram_length,
+                ngram_length,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Format header including `HEADER_MAGIC` and the terminating empty line
+impl fmt::Display for ModelHeader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let marker: String = match self.set_marker {
+            Some(byte) => byte.to_string(),
+            None => String::from("none"),
+        };
+        writeln!(f, "{}", HEADER_MAGIC)?;
+        writeln!(f, "version: {}", self.version)?;
+        writeln!(f, "name: {}", self.name)?;
+        writeln!(f, "alphabet: {}", self.sigma_type.name())?;
+        writeln!(f, "ngram_length: {}", self.ngram_length)?;
+        writeln!(f, "marker: {}", marker)?;
+        writeln!(f, "smoothing: {}", self.smoothing_type.name())?;
+        if let SmoothingType::LidstoneSmoothing(Some(pseudo_counts)) = &self.smoothing_type {
+            let pseudo_counts: Vec<String> =
+                pseudo_counts.iter().map(|pseudo_count| pseudo_count.to_string()).collect();
+            writeln!(f, "pseudo_counts: {}", pseudo_counts.join(","))?;
+        }
+        writeln!(f, "corpus_size: {}", self.corpus_size)?;
+        writeln!(f, "symbol_count: {}", self.symbol_count)?;
+        writeln!(f, "sigma_size: {}", self.sigma_size)?;
+        if let Some(weights) = &self.interpolation_weights {
+            let weights: Vec<String> = weights.iter().map(|weight| weight.to_string()).collect();
+            writeln!(f, "interpolation_weights: {}", weights.join(","))?;
+        }
+        writeln!(f)
+    }
+}
+
+fn parse_number(key: &str, value: &str) -> Result<usize, Error> {
+    value.parse::<usize>().map_err(|_| {
+        Error::model_format(&format!("Illformed value for header key {}: {}", key, value))
+    })
+}
+
+fn parse_weights(key: &str, value: &str) -> Result<Vec<f64>, Error> {
+    value
+        .split(',')
+        .map(|weight| {
+            weight.trim().parse::<f64>().map_err(|_| {
+                Error::model_format(&format!(
+                    "Illformed value for header key {}: {}",
+                    key, value
+                ))
+            })
+        })
+        .collect()
+}
+
+fn missing_key(key: &str) -> Error {
+    Error::model_format(&format!("Missing header key: {}", key))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_model_header1() {
+        let sigma: Sigma = Sigma::new(Some(35), SigmaType::Ascii);
+        let mut header = ModelHeader::new("english", &sigma, 3, &SmoothingType::WittenBellSmoothing);
+        header.corpus_size = 120;
+        header.symbol_count = 100;
+        header.interpolation_weights = Some(vec![0.125, 0.25, 0.625]);
+        let formatted = header.to_string();
+        let lines: Vec<String> = formatted
+            .lines()
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .map(String::from)
+            .collect();
+        assert_eq!(header, ModelHeader::from_lines(&lines).unwrap());
+        assert_eq!(95, header.sigma_size);
+        // pseudo-counts of lidstone smoothing are kept in a key of their own
+        header.smoothing_type = SmoothingType::LidstoneSmoothing(Some(vec![0.5, 0.01]));
+        let formatted = header.to_string();
+        assert!(formatted.contains("smoothing: lidstone\npseudo_counts: 0.5,0.01\n"));
+        let lines: Vec<String> = formatted
+            .lines()
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .map(String::from)
+            .collect();
+        assert_eq!(header, ModelHeader::from_lines(&lines).unwrap());
+    }
+
+    #[test]
+    fn test_model_header2() {
+        let sigma: Sigma = Sigma::new(None, SigmaType::AlphaNum);
+        let header = ModelHeader::new("test", &sigma, 2, &SmoothingType::NoSmoothing);
+        assert!(header.check_compatibility(&sigma, 2).is_ok());
+        assert!(header.check_compatibility(&sigma, 1).is_ok());
+        match header.check_compatibility(&sigma, 3) {
+            Err(Error::NGramLengthMismatch {
+                model_length: 2,
+                ngram_length: 3,
+                ..
+            }) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+        let ascii: Sigma = Sigma::new(None, SigmaType::Ascii);
+        match header.check_compatibility(&ascii, 2) {
+            Err(Error::AlphabetMismatch { sigma, .. }) => assert_eq!(SigmaType::Ascii, sigma),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        let marked: Sigma = Sigma::new(Some(35), SigmaType::AlphaNum);
+        match header.check_compatibility(&marked, 2) {
+            Err(Error::MarkerMismatch { .. }) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
@@ -1,54 +1,108 @@
-use models::errors::NGramModelError;
-use std::collections::HashMap; // for cartesian_product
+use errors::Error;
+use std::collections::HashMap;
 
 /// Hold mapping of ngrams to the related occurency counts
 ///
-/// Occurency counts are stored as `f64` float to allow smoothing.
+/// Occurency counts are stored as `f64` float to allow smoothing. A model is either total, holding
+/// every ngram of the vocabulary, or sparse, holding only ngrams seen in the text. All ngrams
+/// of the vocabulary not held by the model share the same `unseen_count`.
 ///
 /// # Fields
 ///
 /// * `model` - mapping of ngrams to occurence counts
+/// * `vocabulary_size` - number of distinct ngrams possible
+/// * `unseen_count` - occurence count of each ngram not held by the model (zero for raw counts)
 pub struct NGramModel {
     model: HashMap<String, f64>, // float because of smoothing
+    vocabulary_size: usize,
+    unseen_count: f64,
 }
 
 impl NGramModel {
-    pub fn from_ngrams(ngrams: &Vec<String>) -> Result<NGramModel, NGramModelError> {
+    /// Init total model holding every ngram of the vocabulary
+    pub fn from_ngrams(ngrams: &Vec<String>) -> Result<NGramModel, Error> {
         let model: HashMap<String, f64> = ngrams
             .into_iter()
             .map(move |ngram| (ngram.clone(), 0.0))
             .collect::<HashMap<String, f64>>();
-        Ok(NGramModel { model })
+        let vocabulary_size: usize = model.len();
+        Ok(NGramModel {
+            model,
+            vocabulary_size,
+            unseen_count: 0.0,
+        })
     }
 
-    pub fn add_ngrams(&mut self, ngrams: &Vec<String>) -> Result<(), NGramModelError> {
+    /// Init sparse model for a vocabulary of the given size
+    pub fn from_vocabulary_size(vocabulary_size: usize) -> NGramModel {
+        NGramModel {
+            model: HashMap::new(),
+            vocabulary_size,
+            unseen_count: 0.0,
+        }
+    }
+
+    pub fn add_ngrams(&mut self, ngrams: &Vec<String>) -> Result<(), Error> {
         for ngram in ngrams {
             self.add_ngram(&ngram[..])?;
         }
         Ok(())
     }
 
-    pub fn add_ngram(&mut self, ngram: &str) -> Result<(), NGramModelError> {
+    pub fn add_ngram(&mut self, ngram: &str) -> Result<(), Error> {
+        self.add_count(ngram, 1.0)
+    }
+
+    /// Add occurences of an ngram, e.g. counts persisted by a former training
+    pub fn add_count(&mut self, ngram: &str, occurences: f64) -> Result<(), Error> {
         match self.model.get_mut(ngram) {
-            Some(count) => *count += 1.0,
-            None => return Err(NGramModelError::new(&format!("Unknow key: {}", ngram))),
+            Some(count) => *count += occurences,
+            None => {
+                if self.model.len() >= self.vocabulary_size {
+                    return Err(Error::UnknownNGram {
+                        ngram: String::from(ngram),
+                    });
+                }
+                self.model.insert(String::from(ngram), occurences);
+            }
         };
         Ok(())
     }
 
-    /// Provide occurence count for ngram
+    /// Provide occurence count for ngram held by the model
     pub fn get_ngram_count(&self, ngram: &str) -> Option<&f64> {
         self.model.get(ngram)
     }
 
-    /// Provide sum of all ngram counts
+    /// Provide occurence count for any ngram of the vocabulary
+    ///
+    /// Ngrams not held by the model get the `unseen_count`.
+    pub fn count(&self, ngram: &str) -> f64 {
+        match self.get_ngram_count(ngram) {
+            Some(count) => *count,
+            None => self.unseen_count,
+        }
+    }
+
+    /// Provide occurence count of each ngram not held by the model
+    pub fn get_unseen_count(&self) -> f64 {
+        self.unseen_count
+    }
+
+    /// Set occurence count of each ngram not held by the model (used by smoothing)
+    pub fn set_unseen_count(&mut self, unseen_count: f64) {
+        self.unseen_count = unseen_count;
+    }
+
+    /// Provide sum of all ngram counts, including the counts of ngrams not held by the model
     pub fn get_total_ngram_count(&self) -> f64 {
-        self.model.iter().map(|(_, count)| count).sum()
+        let held: f64 = self.model.values().sum();
+        held + self.unseen_count * self.get_missing_type_count() as f64
     }
 
     /// Provide number of distinct ngrams in vocabulary
     pub fn get_vocabulary_size(&self) -> usize {
-        self.model.iter().count()
+        self.vocabulary_size
     }
 
     pub fn get_mut_model(&mut self) -> &mut HashMap<String, f64> {
@@ -62,10 +116,13 @@ impl NGramModel {
 
     /// Provide number of ngrams not seen in text
     pub fn get_unseen_type_count(&self) -> usize {
-        self.model
-            .iter()
-            .filter(|(_, count)| *count == &0.0)
-            .count()
+        self.vocabulary_size
+            .saturating_sub(self.get_seen_type_count())
+    }
+
+    /// Provide number of ngrams of the vocabulary not held by the model
+    pub fn get_missing_type_count(&self) -> usize {
+        self.vocabulary_size.saturating_sub(self.model.len())
     }
 
     /// Provide iterator over ngram model's (ngram, count) pairs
@@ -90,7 +147,7 @@ mod test {
         let mut text_model = TextModel::new(ngram_length, &sigma).unwrap();
         text_model.extend(&text[..]);
         let ngrams = text_model.ngram_iter(ngram_length).collect::<Vec<String>>();
-        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length)).unwrap();
+        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length).unwrap()).unwrap();
         ngram_model.add_ngrams(&ngrams).unwrap();
         assert_eq!(&4.0, ngram_model.get_ngram_count("a").unwrap());
         assert_eq!(&2.0, ngram_model.get_ngram_count("b").unwrap());
@@ -107,7 +164,7 @@ mod test {
         let mut text_model = TextModel::new(ngram_length, &sigma).unwrap();
         text_model.extend(&text[..]);
         let ngrams = text_model.ngram_iter(ngram_length).collect::<Vec<String>>();
-        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length)).unwrap();
+        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length).unwrap()).unwrap();
         ngram_model.add_ngrams(&ngrams).unwrap();
         assert_eq!(&4.0, ngram_model.get_ngram_count("a").unwrap());
         assert_eq!(&2.0, ngram_model.get_ngram_count("b").unwrap());
@@ -124,7 +181,7 @@ mod test {
         let mut text_model = TextModel::new(ngram_length, &sigma).unwrap();
         text_model.extend(&text[..]);
         let ngrams = text_model.ngram_iter(ngram_length).collect::<Vec<String>>();
-        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length)).unwrap();
+        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length).unwrap()).unwrap();
         ngram_model.add_ngrams(&ngrams).unwrap();
         assert_eq!(&2.0, ngram_model.get_ngram_count("aa").unwrap());
         assert_eq!(&1.0, ngram_model.get_ngram_count("ab").unwrap());
@@ -145,7 +202,7 @@ mod test {
         let mut text_model = TextModel::new(ngram_length, &sigma).unwrap();
         text_model.extend(&text[..]);
         let ngrams = text_model.ngram_iter(ngram_length).collect::<Vec<String>>();
-        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length)).unwrap();
+        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length).unwrap()).unwrap();
         ngram_model.add_ngrams(&ngrams).unwrap();
         assert_eq!(&2.0, ngram_model.get_ngram_count("aa").unwrap());
         assert_eq!(&1.0, ngram_model.get_ngram_count("ab").unwrap());
@@ -174,7 +231,7 @@ mod test {
         let mut text_model = TextModel::new(ngram_length, &sigma).unwrap();
         text_model.extend(&text[..]);
         let ngrams = text_model.ngram_iter(ngram_length).collect::<Vec<String>>();
-        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length)).unwrap();
+        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length).unwrap()).unwrap();
         ngram_model.add_ngrams(&ngrams).unwrap();
         let count: f64 = ngram_model.get_total_ngram_count();
         assert_eq!(5.0, count);
@@ -189,7 +246,7 @@ mod test {
         let mut text_model = TextModel::new(ngram_length, &sigma).unwrap();
         text_model.extend(&text[..]);
         let ngrams = text_model.ngram_iter(ngram_length).collect::<Vec<String>>();
-        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length)).unwrap();
+        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length).unwrap()).unwrap();
         ngram_model.add_ngrams(&ngrams).unwrap();
         let count: usize = ngram_model.get_vocabulary_size();
         assert_eq!(9, count);
@@ -204,7 +261,7 @@ mod test {
         let mut text_model = TextModel::new(ngram_length, &sigma).unwrap();
         text_model.extend(&text[..]);
         let ngrams = text_model.ngram_iter(ngram_length).collect::<Vec<String>>();
-        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length)).unwrap();
+        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length).unwrap()).unwrap();
         ngram_model.add_ngrams(&ngrams).unwrap();
         let count: usize = ngram_model.get_seen_type_count();
         assert_eq!(3, count);
@@ -219,7 +276,7 @@ mod test {
         let mut text_model = TextModel::new(ngram_length, &sigma).unwrap();
         text_model.extend(&text[..]);
         let ngrams = text_model.ngram_iter(ngram_length).collect::<Vec<String>>();
-        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length)).unwrap();
+        let mut ngram_model = NGramModel::from_ngrams(&sigma.ngrams(ngram_length).unwrap()).unwrap();
         ngram_model.add_ngrams(&ngrams).unwrap();
         let count: usize = ngram_model.get_unseen_type_count();
         assert_eq!(6, count);
@@ -1,13 +1,62 @@
+use models::binary_model::{is_binary_model, write_binary_model, MappedTable};
 use models::count_model::CountModel;
-use models::errors::ProbabilityModelError;
+use errors::Error;
+use models::model_header::{ModelHeader, HEADER_MAGIC};
 use models::ngram_model::NGramModel;
-use regex::Regex;
-use std::collections::HashMap;
+use models::symbol::{split_first_symbol, split_last_symbol, Symbol, UNSEEN_SYMBOL};
+use smoothing::{
+    conditional_probability, context_statistics, continuation_counts, ContextStatistics,
+    Discounts, GoodTuringEstimates, SmoothingType,
+};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io;
 // necessary import for .lines() method of BufReader
 use std::io::prelude::*;
+use std::path::Path;
 
+/// Maximal deviation from one tolerated when checking a model's normalization
+pub const NORMALIZATION_TOLERANCE: f64 = 1e-6;
+
+/// Storage formats of a probability model's mapping
+///
+/// # Table
+///
+/// Mapping of ngrams on their occurence probability held in memory, e.g. read from text dump.
+///
+/// # Mapped
+///
+/// Sorted ngram table with log2 probabilities read through a memory map from binary dump.
+pub enum ModelStorage {
+    Table(HashMap<String, f64>),
+    Mapped(MappedTable),
+}
+
+/// File formats of a probability model dump
+///
+/// # Tsv
+///
+/// Text lines holding ngram and probability separated by tab.
+///
+/// # Binary
+///
+/// Binary dump of sorted ngrams and log2 probabilities, loaded through a memory map.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ModelFormat {
+    Tsv,
+    Binary,
+}
+
+impl ModelFormat {
+    /// Parse model format from its name as used on the cli
+    pub fn from_name(name: &str) -> Option<ModelFormat> {
+        match name {
+            "tsv" => Some(ModelFormat::Tsv),
+            "binary" => Some(ModelFormat::Binary),
+            _ => None,
+        }
+    }
+}
 
 /// Mapping of ngrams on there occurence probability
 ///
@@ -15,149 +64,651 @@ use std::io::prelude::*;
 ///
 /// `model` -  mapping of ngrams on their occurence probability
 /// `name` - probability model name (related to modeled language)
+/// `header` - settings the model was built with (missing for models without header)
+/// `backs_off` - unseen ngrams are estimated from shorter ngrams, see `backoff`
 pub struct ProbabilityModel {
-    model: HashMap<String, f64>,
+    model: ModelStorage,
     pub name: String,
+    pub header: Option<ModelHeader>,
+    backs_off: bool,
 }
 
 impl ProbabilityModel {
     /// Init empty model with the given `name`
-    pub fn from_name(name: &str) -> Result<ProbabilityModel, ProbabilityModelError> {
+    pub fn from_name(name: &str) -> Result<ProbabilityModel, Error> {
         let name: String = String::from(name);
-        let model: HashMap<String, f64> = HashMap::new();
-        return Ok(ProbabilityModel { name, model });
+        let model = ModelStorage::Table(HashMap::new());
+        return Ok(ProbabilityModel {
+            name,
+            model,
+            header: None,
+            backs_off: false,
+        });
     }
 
     /// Load probability model from probability model dump
-    pub fn from_file(path: &str) -> Result<ProbabilityModel, ProbabilityModelError> {
-        let name = ProbabilityModel::parse_name_from_path(path)?;
-        let mut model: HashMap<String, f64> = HashMap::new();
-        let f = fs::File::open(path)?;
-        let reader = io::BufReader::new(f);
-        for line in reader.lines() {
-            let mut split = line // looks like: abc\t0.123
-                .as_ref() // 'line' needs to outlive 'split'
-                .unwrap()
-                .split("\t"); // split into: [abc, 0.123]
-            let ngram = match split.next() {
-                Some(ngram) => String::from(ngram),
-                None => panic!("Illformed line in model: {}", &name[..]),
-            };
-            let probability: f64 = match split.next() {
-                Some(raw) => raw.parse().unwrap(),
-                None => panic!("Illformed prabability in model: {}", &name[..]),
-            };
-            model.insert(ngram, probability);
-        }
-        Ok(ProbabilityModel { name, model })
+    ///
+    /// The dump format is detected from the file content. If the dump starts with a model header,
+    /// the model name is taken from the header. Errors carry the path of the dump and, for
+    /// ill-formed lines of text dumps, the line number.
+    pub fn from_file(path: &str) -> Result<ProbabilityModel, Error> {
+        let loaded = match is_binary_model(path) {
+            Ok(true) => ProbabilityModel::from_binary_file(path),
+            Ok(false) => ProbabilityModel::from_text_file(path),
+            Err(err) => Err(err),
+        };
+        loaded.map_err(|err| err.in_file(path))
+    }
+
+    /// Load probability model from text probability model dump, see `from_file`
+    fn from_text_file(path: &str) -> Result<ProbabilityModel, Error> {
+        let (header, entries) = read_text_dump(path)?;
+        let name: String = match &header {
+            Some(header) => header.name.clone(),
+            None => ProbabilityModel::parse_name_from_path(path)?,
+        };
+        let model: HashMap<String, f64> = entries.into_iter().collect();
+        let backs_off: bool = backs_off(&header);
+        Ok(ProbabilityModel {
+            name,
+            model: ModelStorage::Table(model),
+            header,
+            backs_off,
+        })
+    }
+
+    /// Load probability model from binary probability model dump through a memory map
+    pub fn from_binary_file(path: &str) -> Result<ProbabilityModel, Error> {
+        let (header, table) = MappedTable::open(path).map_err(|err| err.in_file(path))?;
+        let name: String = match &header {
+            Some(header) => header.name.clone(),
+            None => ProbabilityModel::parse_name_from_path(path)?,
+        };
+        let backs_off: bool = backs_off(&header);
+        Ok(ProbabilityModel {
+            name,
+            model: ModelStorage::Mapped(table),
+            header,
+            backs_off,
+        })
     }
 
-    /// Add unigram probabilities from count model
-    pub fn add_unigram_probabilities(
+    /// Set settings the model is built with, they are written in front of the model dump
+    pub fn set_header(&mut self, header: ModelHeader) {
+        self.backs_off = header.smoothing_type.backs_off();
+        self.header = Some(header);
+    }
+
+    /// Add probabilities of all ngram lengths from count model as required by `smoothing_type`
+    pub fn add_probabilities(
         &mut self,
         count_model: &CountModel,
-    ) -> Result<(), ProbabilityModelError> {
-        let unigram_counts: &NGramModel = match count_model.get_ngram_model(1) {
-            Some(counts) => counts,
-            None => {
-                return Err(ProbabilityModelError::new(&format!(
-                    "No unigram model found"
-                )))
+        smoothing_type: &SmoothingType,
+    ) -> Result<(), Error> {
+        match smoothing_type {
+            SmoothingType::KneserNeySmoothing => self.add_kneser_ney_probabilities(count_model),
+            SmoothingType::AbsoluteDiscounting => {
+                self.add_absolute_discounting_probabilities(count_model)
             }
-        };
-        self.calc_and_add_unigram_probabilities(unigram_counts)
+            SmoothingType::GoodTuringSmoothing => self.add_good_turing_probabilities(count_model),
+            _ => self.add_context_probabilities(count_model, smoothing_type),
+        }
     }
 
-    /// Calculate unigram probabilities from unigram counts
+    /// Add interpolated modified Kneser-Ney probabilities from (unsmoothed) count model
     ///
-    /// Calculation is done by dividing:
-    /// |ngram| - count of unigram (e.g. |a| = 5)
-    /// |total| - sum of all unigram counts (e.g. |total| = 100)
+    /// Calculation for ngram `hw` with prefix (context) `h` and shortened prefix `h'`:
     ///
-    ///  probability = |ngram| / |total|
+    ///  P(w|h) = max(|hw| - D(|hw|), 0) / |h*| + gamma(h) * P(w|h')
+    ///  gamma(h) = (D_1 * N_1(h*) + D_2 * N_2(h*) + D_3+ * N_3+(h*)) / |h*|
     ///
-    fn calc_and_add_unigram_probabilities(
+    /// with `|h*|` being the sum of counts of ngrams with prefix `h` and `N_r(h*)` the count of
+    /// those seen `r` times. The longest ngrams use raw counts, shorter ones continuation counts.
+    /// The recursion ends with the uniform distribution over sigma.
+    ///
+    /// Only seen ngrams are stored with their probability. The weight `gamma(h)` is stored as
+    /// prefix followed by `UNSEEN_SYMBOL` (e.g. `ab\u{FFFD}`) and the uniform share of the
+    /// unigram distribution as `UNSEEN_SYMBOL`. Probabilities of unseen ngrams are derived on
+    /// lookup, see `backoff`.
+    pub fn add_kneser_ney_probabilities(
         &mut self,
-        unigram_model: &NGramModel,
-    ) -> Result<(), ProbabilityModelError> {
-        let total: f64 = unigram_model.get_total_ngram_count();
-        for (ngram, count) in unigram_model.iter() {
-            let prob: f64 = count / total;
-            self.model.insert(ngram.to_string(), prob);
+        count_model: &CountModel,
+    ) -> Result<(), Error> {
+        let max_ngram_length: usize = count_model.max_ngram_length();
+        for ngram_length in 1..=max_ngram_length {
+            let raw_counts: &NGramModel = count_model
+                .get_ngram_model(ngram_length)
+                .ok_or_else(|| {
+                    Error::model(&format!(
+                        "No count model found for ngram length: {}",
+                        ngram_length
+                    ))
+                })?;
+            let continuation_model: NGramModel;
+            let counts: &NGramModel = if ngram_length == max_ngram_length {
+                raw_counts
+            } else {
+                let longer_counts = count_model
+                    .get_ngram_model(ngram_length + 1)
+                    .expect("Count model holds all ngram lengths up to the max");
+                continuation_model =
+                    continuation_counts(longer_counts, raw_counts.get_vocabulary_size());
+                &continuation_model
+            };
+            let discounts = Discounts::from_ngram_model(counts);
+            let entries = self.calc_discounted_probabilities(
+                raw_counts,
+                counts,
+                &discounts,
+                ngram_length == 1,
+            )?;
+            self.table_mut()?.extend(entries);
         }
+        self.backs_off = true;
         Ok(())
     }
 
-    /// Add ngram probabilities from count model
-    pub fn add_ngram_probabilities(
+    /// Add interpolated absolute discounting probabilities from (unsmoothed) count model
+    ///
+    /// Calculation for ngram `hw` with prefix (context) `h` and shortened prefix `h'`:
+    ///
+    ///  P(w|h) = max(|hw| - D, 0) / |h*| + gamma(h) * P(w|h')
+    ///  gamma(h) = D * N_1+(h*) / |h*|
+    ///
+    /// with `|h*|` being the sum of counts of ngrams with prefix `h` and `N_1+(h*)` the count of
+    /// those seen at least once. `D` is estimated per ngram length, see `Discounts::absolute`.
+    /// Unlike Kneser-Ney smoothing all ngram lengths use raw counts. The recursion ends with
+    /// the uniform distribution over sigma. Entries are stored as for Kneser-Ney smoothing.
+    pub fn add_absolute_discounting_probabilities(
         &mut self,
         count_model: &CountModel,
-    ) -> Result<(), ProbabilityModelError> {
-        for (prefix_model, ngram_model) in count_model.iter_tuple() {
-            self.calc_and_add_ngram_probabilites(prefix_model, ngram_model)?;
+    ) -> Result<(), Error> {
+        for ngram_length in 1..=count_model.max_ngram_length() {
+            let counts: &NGramModel = count_model
+                .get_ngram_model(ngram_length)
+                .ok_or_else(|| {
+                    Error::model(&format!(
+                        "No count model found for ngram length: {}",
+                        ngram_length
+                    ))
+                })?;
+            let discounts = Discounts::absolute(counts);
+            let entries =
+                self.calc_discounted_probabilities(counts, counts, &discounts, ngram_length == 1)?;
+            self.table_mut()?.extend(entries);
         }
+        self.backs_off = true;
         Ok(())
     }
 
-    /// Calculate ngram probabilities from ngram counts
+    /// Calculate interpolated probabilities of one ngram length from discounted counts
+    ///
+    /// See `add_kneser_ney_probabilities` and `add_absolute_discounting_probabilities`. Shorter
+    /// ngrams have to be added to the model before.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw_counts` - counts of the ngrams of the text, unigrams hold all sigma symbols
+    /// * `counts` - counts the probabilities are based on (raw or continuation counts)
+    /// * `discounts` - discounts subtracted from `counts`
+    /// * `is_unigram_level` - the counts are unigram counts
+    fn calc_discounted_probabilities(
+        &mut self,
+        raw_counts: &NGramModel,
+        counts: &NGramModel,
+        discounts: &Discounts,
+        is_unigram_level: bool,
+    ) -> Result<Vec<(String, f64)>, Error> {
+        let contexts = context_statistics(counts);
+        let uniform: f64 = 1.0 / raw_counts.get_vocabulary_size() as f64;
+        let table = self.table_mut()?;
+        let lookup = |key: &str| table.get(key).cloned();
+        let lower_order = |ngram: &str| -> f64 {
+            let (_, suffix) = split_first_symbol(ngram);
+            match suffix.is_empty() {
+                true => uniform,
+                false => backoff(suffix, &lookup, false).unwrap_or(0.0),
+            }
+        };
+        let mut entries: Vec<(String, f64)> = Vec::new();
+        for (prefix, statistics) in contexts.iter() {
+            let weight: f64 = statistics.discounted_mass(discounts) / statistics.total;
+            match prefix.is_empty() {
+                // unseen unigrams share the uniform part of the distribution
+                true => entries.push((unseen_ngram("", 1), weight * uniform)),
+                false => entries.push((unseen_ngram(prefix, 1), weight)),
+            }
+        }
+        if is_unigram_level && contexts.is_empty() {
+            entries.push((unseen_ngram("", 1), uniform));
+        }
+        // all unigrams of sigma are stored, even if never seen
+        let ngrams = raw_counts
+            .iter()
+            .filter(|_| is_unigram_level)
+            .chain(counts.iter().filter(|_| !is_unigram_level));
+        for (ngram, _) in ngrams {
+            let count: f64 = counts.count(ngram);
+            let (prefix, _) = split_last_symbol(ngram);
+            let prob: f64 = match contexts.get(prefix) {
+                Some(statistics) if count > 0.0 => {
+                    let weight: f64 = statistics.discounted_mass(discounts) / statistics.total;
+                    let discounted: f64 = (count - discounts.get(count)).max(0.0) / statistics.total;
+                    discounted + weight * lower_order(ngram)
+                }
+                Some(statistics) => {
+                    statistics.discounted_mass(discounts) / statistics.total * lower_order(ngram)
+                }
+                None => lower_order(ngram),
+            };
+            entries.push((ngram.clone(), prob));
+        }
+        Ok(entries)
+    }
+
+    /// Add Simple Good-Turing probabilities from (unsmoothed) count model
     ///
-    /// Calculation is done by dividing:
-    /// |ngram| - count of ngram (e.g. |abc| = 5)
-    /// |prefix| - count of ngram prefix (e.g. |ab| = 7)
+    /// The smoothed counts `r*` of each ngram length are estimated from its frequency of
+    /// frequencies, see `GoodTuringEstimates`. Calculation for ngram `hw` with prefix (context) `h`:
     ///
-    ///  probability = |ngram| / |prefix|
+    ///  P(w|h) = |hw|* / |h*|
     ///
-    fn calc_and_add_ngram_probabilites(
+    /// with `|h*|` being the sum of raw counts of ngrams with prefix `h`. The remaining mass of
+    /// the context is shared by the symbols never seen after `h` and stored as prefix followed
+    /// by `UNSEEN_SYMBOL` (e.g. `ab\u{FFFD}`), for unigrams as probability of the unseen symbols.
+    /// If all symbols were seen after `h`, the smoothed counts are normalized to sum up to one.
+    /// Ngrams with an unseen prefix get the uniform probability over sigma.
+    pub fn add_good_turing_probabilities(
         &mut self,
-        prefix_model: &NGramModel,
-        ngram_model: &NGramModel,
-    ) -> Result<(), ProbabilityModelError> {
-        for (ngram, count) in ngram_model.iter() {
-            let prefix: String = ngram[..ngram.len() - 1].to_string();
-            let denominator: f64 = match prefix_model.get_ngram_count(&prefix[..]) {
-                Some(count) => *count,
-                None => {
-                    return Err(ProbabilityModelError::new(&format!(
-                        "Ngram model doesn't know: {}",
-                        prefix
-                    )))
+        count_model: &CountModel,
+    ) -> Result<(), Error> {
+        let unigram_counts: &NGramModel = count_model
+            .get_ngram_model(1)
+            .ok_or_else(|| Error::model("No unigram model found"))?;
+        let sigma_size: f64 = unigram_counts.get_vocabulary_size() as f64;
+        for ngram_length in 1..=count_model.max_ngram_length() {
+            let counts: &NGramModel = count_model
+                .get_ngram_model(ngram_length)
+                .ok_or_else(|| {
+                    Error::model(&format!(
+                        "No count model found for ngram length: {}",
+                        ngram_length
+                    ))
+                })?;
+            let estimates = GoodTuringEstimates::from_ngram_model(counts);
+            // raw total, smoothed total and count of seen ngrams per context
+            let mut contexts: HashMap<&str, (f64, f64, f64)> = HashMap::new();
+            for (ngram, count) in counts.iter().filter(|(_, count)| **count > 0.0) {
+                let (prefix, _) = split_last_symbol(ngram);
+                let context = contexts.entry(prefix).or_insert((0.0, 0.0, 0.0));
+                context.0 += count;
+                context.1 += estimates.count(*count);
+                context.2 += 1.0;
+            }
+            let unseen_prob = |(total, smoothed, seen): &(f64, f64, f64)| -> f64 {
+                match seen < &sigma_size {
+                    true => (1.0 - smoothed / total).max(0.0) / (sigma_size - seen),
+                    false => 0.0,
                 }
             };
-            self.model.insert(String::from(ngram), count / denominator);
+            let mut entries: Vec<(String, f64)> = Vec::new();
+            for (ngram, count) in counts.iter() {
+                let (prefix, _) = split_last_symbol(ngram);
+                let prob: f64 = match contexts.get(prefix) {
+                    Some(context) if *count > 0.0 => {
+                        let (total, smoothed, seen) = context;
+                        let denominator = if seen < &sigma_size { total } else { smoothed };
+                        estimates.count(*count) / denominator
+                    }
+                    Some(context) => unseen_prob(context),
+                    None => 1.0 / sigma_size,
+                };
+                entries.push((ngram.clone(), prob));
+            }
+            if ngram_length > 1 {
+                // prefixes followed by every symbol leave nothing to unseen ngrams
+                for (prefix, context) in contexts.iter().filter(|(_, c)| c.2 < sigma_size) {
+                    entries.push((unseen_ngram(prefix, 1), unseen_prob(context)));
+                }
+                entries.push((unseen_ngram("", ngram_length), 1.0 / sigma_size));
+            }
+            self.table_mut()?.extend(entries);
         }
         Ok(())
     }
 
-    pub fn get(&self, ngram: &str) -> Option<&f64> {
-        self.model.get(ngram)
+    /// Add probabilities of the smoothing types based on the counts of one ngram length
+    ///
+    /// The distribution of the symbols following each prefix (context) is smoothed on its own,
+    /// see `smoothing::conditional_probability`, so it sums up to one. The probability of the
+    /// symbols never seen after a prefix is stored as prefix followed by `UNSEEN_SYMBOL` (e.g.
+    /// `ab\u{FFFD}`), unless all symbols were seen after it. The one of ngrams with an unseen
+    /// prefix is stored as ngram made of `UNSEEN_SYMBOL` only. Unigrams hold all sigma symbols,
+    /// even if never seen.
+    pub fn add_context_probabilities(
+        &mut self,
+        count_model: &CountModel,
+        smoothing_type: &SmoothingType,
+    ) -> Result<(), Error> {
+        let unigram_counts: &NGramModel = count_model
+            .get_ngram_model(1)
+            .ok_or_else(|| Error::model("No unigram model found"))?;
+        let sigma_size: f64 = unigram_counts.get_vocabulary_size() as f64;
+        let unseen_context = ContextStatistics::default();
+        for ngram_length in 1..=count_model.max_ngram_length() {
+            let counts: &NGramModel = count_model
+                .get_ngram_model(ngram_length)
+                .ok_or_else(|| {
+                    Error::model(&format!(
+                        "No count model found for ngram length: {}",
+                        ngram_length
+                    ))
+                })?;
+            let contexts = context_statistics(counts);
+            let prob = |count: f64, context: &ContextStatistics| {
+                conditional_probability(smoothing_type, ngram_length, count, context, sigma_size)
+            };
+            let mut entries: Vec<(String, f64)> = Vec::new();
+            for (ngram, count) in counts.iter() {
+                let (prefix, _) = split_last_symbol(ngram);
+                let context = contexts.get(prefix).unwrap_or(&unseen_context);
+                entries.push((ngram.clone(), prob(*count, context)?));
+            }
+            if ngram_length > 1 {
+                // prefixes followed by every symbol leave nothing to unseen ngrams
+                for (prefix, context) in contexts.iter().filter(|(_, c)| c.seen() < sigma_size) {
+                    entries.push((unseen_ngram(prefix, 1), prob(0.0, context)?));
+                }
+                let unseen_prefix = unseen_ngram("", ngram_length);
+                entries.push((unseen_prefix, prob(0.0, &unseen_context)?));
+            }
+            self.table_mut()?.extend(entries);
+        }
+        Ok(())
     }
 
-    pub fn write_to_file(self, path: &str) -> Result<(), ProbabilityModelError> {
+    /// Provide occurence probability of ngram
+    ///
+    /// Ngrams not held by the model are looked up as unseen ngrams, see `resolve`.
+    pub fn get(&self, ngram: &str) -> Option<f64> {
+        match &self.model {
+            ModelStorage::Table(table) => self.resolve(ngram, |key| table.get(key).cloned(), false),
+            ModelStorage::Mapped(table) => self
+                .resolve(ngram, |key| table.get_log(key), true)
+                .map(|log| log.exp2()),
+        }
+    }
+
+    /// Provide log2 of occurence probability of ngram
+    pub fn get_log(&self, ngram: &str) -> Option<f64> {
+        match &self.model {
+            ModelStorage::Table(_) => self.get(ngram).map(|prob| prob.log2()),
+            ModelStorage::Mapped(table) => self.resolve(ngram, |key| table.get_log(key), true),
+        }
+    }
+
+    /// Provide occurence probability of ngram only if the model holds the ngram itself
+    ///
+    /// In contrast to `get` there is no fallback to the entries of unseen ngrams.
+    pub fn get_exact(&self, ngram: &str) -> Option<f64> {
+        match &self.model {
+            ModelStorage::Table(table) => table.get(ngram).cloned(),
+            ModelStorage::Mapped(table) => table.get_log(ngram).map(|log| log.exp2()),
+        }
+    }
+
+    /// Look up ngram, falling back to the entries of unseen ngrams of sparse models
+    ///
+    /// The lookup order is:
+    ///
+    /// 1. the ngram itself, e.g. `abc`
+    /// 2. the unseen ngram of the prefix, e.g. `ab\u{FFFD}`
+    /// 3. the unseen ngram of an unseen prefix, e.g. `\u{FFFD}\u{FFFD}\u{FFFD}`
+    ///
+    /// Models backing off to shorter ngrams are looked up by `backoff` instead.
+    fn resolve<F>(&self, ngram: &str, lookup: F, log_space: bool) -> Option<f64>
+    where
+        F: Fn(&str) -> Option<f64>,
+    {
+        if self.backs_off {
+            return backoff(ngram, &lookup, log_space);
+        }
+        if let Some(value) = lookup(ngram) {
+            return Some(value);
+        }
+        let (prefix, _) = split_last_symbol(ngram);
+        lookup(&unseen_ngram(prefix, 1))
+            .or_else(|| lookup(&unseen_ngram("", ngram.chars().count())))
+    }
+
+    /// Provide all (ngram, probability) pairs of the model
+    pub fn entries(&self) -> Vec<(String, f64)> {
+        match &self.model {
+            ModelStorage::Table(table) => table
+                .iter()
+                .map(|(ngram, prob)| (ngram.clone(), *prob))
+                .collect(),
+            ModelStorage::Mapped(table) => (0..table.ngram_count())
+                .map(|idx| (table.key(idx).into_owned(), table.log_probability(idx).exp2()))
+                .collect(),
+        }
+    }
+
+    /// Provide symbols the model holds unigram probabilities for
+    pub fn symbols(&self) -> Vec<Symbol> {
+        self.entries()
+            .into_iter()
+            .filter(|(ngram, _)| ngram.chars().count() == 1 && !ngram.contains(UNSEEN_SYMBOL))
+            .map(|(ngram, _)| Symbol::from_str(&ngram))
+            .collect()
+    }
+
+    /// Check that the distribution following each prefix (context) of the model sums up to one
+    ///
+    /// The contexts checked are the empty one (unigrams) and the prefixes of all ngrams the model
+    /// holds, including the ones of unseen ngram entries. Contexts without any probability mass,
+    /// e.g. unseen prefixes of unsmoothed models, are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `tolerance` - maximal deviation of a context's sum from one
+    ///
+    /// Returns the sorted (prefix, sum) pairs of all contexts deviating more than `tolerance`.
+    pub fn check_normalization(&self, tolerance: f64) -> Vec<(String, f64)> {
+        let entries = self.entries();
+        let symbols: Vec<&str> = entries
+            .iter()
+            .map(|(ngram, _)| &ngram[..])
+            .filter(|ngram| ngram.chars().count() == 1 && !ngram.contains(UNSEEN_SYMBOL))
+            .collect();
+        let mut prefixes: HashSet<&str> = entries
+            .iter()
+            .filter(|(ngram, _)| ngram.chars().count() > 1)
+            .map(|(ngram, _)| split_last_symbol(ngram).0)
+            .collect();
+        prefixes.insert("");
+        let mut deviations: Vec<(String, f64)> = prefixes
+            .into_iter()
+            .map(|prefix| {
+                let sum: f64 = symbols
+                    .iter()
+                    .filter_map(|symbol| self.get(&format!("{}{}", prefix, symbol)))
+                    .sum();
+                (prefix.to_string(), sum)
+            })
+            .filter(|(_, sum)| *sum != 0.0 && (sum - 1.0).abs() > tolerance)
+            .collect();
+        deviations.sort_by(|a, b| a.0.cmp(&b.0));
+        deviations
+    }
+
+    /// Write model as text dump, a line per ngram and probability
+    pub fn write_to_file(self, path: &str) -> Result<(), Error> {
         let mut write_buf = String::new();
-        for (ngram, prob) in self.model {
+        if let Some(header) = &self.header {
+            write_buf.push_str(&header.to_string());
+        }
+        for (ngram, prob) in self.entries() {
             write_buf.push_str(&format!("{}\t{}", ngram, prob));
-            write_buf.push_str(&String::from("\n"));
+            write_buf.push('\n');
         }
-        fs::write(path, &write_buf)?;
-        Ok(())
+        fs::write(path, &write_buf).map_err(|err| Error::from(err).in_file(path))
     }
 
-    /// Parse model name from file name
-    pub fn parse_name_from_path(path: &str) -> Result<String, ProbabilityModelError> {
-        // only build regex once
-        lazy_static! {
-            static ref GET_NAME: Regex =
-                Regex::new(r"\./data(?:/models(/ascii|/alphanum)?)?/([a-zA-Z0-9_]+)\.model").unwrap();
+    /// Write model as binary dump to be loaded through a memory map
+    pub fn write_to_binary_file(self, path: &str) -> Result<(), Error> {
+        let entries: Vec<(String, f64)> = match &self.model {
+            ModelStorage::Table(table) => table
+                .iter()
+                .map(|(ngram, prob)| (ngram.clone(), prob.log2()))
+                .collect(),
+            ModelStorage::Mapped(table) => (0..table.ngram_count())
+                .map(|idx| (table.key(idx).into_owned(), table.log_probability(idx)))
+                .collect(),
         };
-        Ok(String::from(
-            &GET_NAME
-                .captures_iter(path)
-                .next()
-                .expect("Can't parse model name from path")[2],
-        ))
+        write_binary_model(path, self.header.as_ref(), entries)
+    }
+
+    /// Write model in the given dump format
+    pub fn write_to_file_as(
+        self,
+        path: &str,
+        format: &ModelFormat,
+    ) -> Result<(), Error> {
+        match format {
+            ModelFormat::Tsv => self.write_to_file(path),
+            ModelFormat::Binary => self.write_to_binary_file(path),
+        }
+    }
+
+    fn table_mut(&mut self) -> Result<&mut HashMap<String, f64>, Error> {
+        match &mut self.model {
+            ModelStorage::Table(table) => Ok(table),
+            ModelStorage::Mapped(_) => Err(Error::model(
+                "Memory mapped model can't be changed",
+            )),
+        }
+    }
+
+    /// Parse model name from file name, which is the file stem (e.g. `english` for
+    /// `/opt/models/english.model`)
+    pub fn parse_name_from_path(path: &str) -> Result<String, Error> {
+        Path::new(path)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .filter(|stem| !stem.is_empty())
+            .map(String::from)
+            .ok_or_else(|| {
+                Error::model(&format!("Can't parse model name from path: {}", path))
+            })
+    }
+}
+
+/// Look up ngram of a model backing off to shorter ngrams
+///
+/// Unseen ngrams are estimated from the ngram without its first symbol, weighted by the entry
+/// of the prefix followed by `UNSEEN_SYMBOL` (e.g. `P(abc) = w(ab\u{FFFD}) * P(bc)`). Unseen
+/// prefixes carry no weight. Unseen unigrams are looked up as `UNSEEN_SYMBOL`.
+///
+/// # Arguments
+///
+/// * `ngram` - ngram to look up
+/// * `lookup` - lookup of stored entries
+/// * `log_space` - stored entries are log2 probabilities
+fn backoff<F>(ngram: &str, lookup: &F, log_space: bool) -> Option<f64>
+where
+    F: Fn(&str) -> Option<f64>,
+{
+    if let Some(value) = lookup(ngram) {
+        return Some(value);
+    }
+    let (prefix, _) = split_last_symbol(ngram);
+    if prefix.is_empty() {
+        return lookup(&unseen_ngram("", 1));
+    }
+    let lower_order: f64 = backoff(split_first_symbol(ngram).1, lookup, log_space)?;
+    match lookup(&unseen_ngram(prefix, 1)) {
+        Some(weight) if log_space => Some(weight + lower_order),
+        Some(weight) => Some(weight * lower_order),
+        None => Some(lower_order),
     }
 }
 
+/// Optional header and (ngram, value) entries of a text dump
+pub type TextDump = (Option<ModelHeader>, Vec<(String, f64)>);
+
+/// Read text dump of ngram values, an optional header followed by lines like: abc\t0.123
+///
+/// Text dumps hold the probabilities of a `ProbabilityModel` or the raw counts of a
+/// `CountModel`. Errors of ill-formed lines carry the line number.
+pub fn read_text_dump(path: &str) -> Result<TextDump, Error> {
+    let mut entries: Vec<(String, f64)> = Vec::new();
+    let f = fs::File::open(path)?;
+    let reader = io::BufReader::new(f);
+    // line numbers start at one
+    let mut lines = reader
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line))
+        .peekable();
+    let header: Option<ModelHeader> = match lines.peek() {
+        Some((_, Ok(first))) if first == HEADER_MAGIC => {
+            lines.next();
+            let mut header_lines: Vec<String> = Vec::new();
+            for (line_number, line) in lines.by_ref() {
+                let line = line.map_err(|err| Error::from(err).at_line(line_number))?;
+                if line.is_empty() {
+                    break;
+                }
+                header_lines.push(line);
+            }
+            Some(ModelHeader::from_lines(&header_lines)?)
+        }
+        _ => None,
+    };
+    for (line_number, line) in lines {
+        let line = line.map_err(|err| Error::from(err).at_line(line_number))?;
+        entries.push(parse_entry(&line).map_err(|err| err.at_line(line_number))?);
+    }
+    Ok((header, entries))
+}
+
+/// Parse entry line of a text dump, see `read_text_dump`
+fn parse_entry(line: &str) -> Result<(String, f64), Error> {
+    let mut split = line.split('\t');
+    let ngram: &str = split.next().unwrap_or("");
+    let value: f64 = match split.next() {
+        Some(raw) => raw.parse().map_err(|err| Error::ModelFormat {
+            path: None,
+            line: None,
+            details: format!("Illformed value of ngram {}: {}", ngram, raw),
+            source: Some(Box::new(err)),
+        })?,
+        None => {
+            return Err(Error::model_format(&format!(
+                "Illformed line, expected <ngram>\\t<value>: {}",
+                line
+            )))
+        }
+    };
+    Ok((String::from(ngram), value))
+}
+
+/// Check if the model described by `header` backs off to shorter ngrams
+fn backs_off(header: &Option<ModelHeader>) -> bool {
+    match header {
+        Some(header) => header.smoothing_type.backs_off(),
+        None => false,
+    }
+}
+
+/// Build key of unseen ngrams by appending `count` times `UNSEEN_SYMBOL` to `prefix`
+fn unseen_ngram(prefix: &str, count: usize) -> String {
+    let mut ngram = String::from(prefix);
+    ngram.push_str(&UNSEEN_SYMBOL.to_string().repeat(count));
+    ngram
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -179,11 +730,12 @@ mod test {
         count_model
             .count_ngrams_from_text_model(&text_model)
             .unwrap();
-        count_model.smooth(&smoothing_type).unwrap();
-        probability_model.add_unigram_probabilities(&count_model).unwrap();
-        assert_eq!(&(4.0 / 7.0), probability_model.get("a").unwrap());
-        assert_eq!(&(2.0 / 7.0), probability_model.get("b").unwrap());
-        assert_eq!(&(1.0 / 7.0), probability_model.get("c").unwrap());
+        probability_model
+            .add_probabilities(&count_model, &smoothing_type)
+            .unwrap();
+        assert_eq!((4.0 / 7.0), probability_model.get("a").unwrap());
+        assert_eq!((2.0 / 7.0), probability_model.get("b").unwrap());
+        assert_eq!((1.0 / 7.0), probability_model.get("c").unwrap());
     }
 
     #[test]
@@ -201,11 +753,12 @@ mod test {
         count_model
             .count_ngrams_from_text_model(&text_model)
             .unwrap();
-        count_model.smooth(&smoothing_type).unwrap();
-        probability_model.add_unigram_probabilities(&count_model).unwrap();
-        assert_eq!(&(4.0 / 9.0), probability_model.get("a").unwrap());
-        assert_eq!(&(2.0 / 9.0), probability_model.get("b").unwrap());
-        assert_eq!(&(1.0 / 9.0), probability_model.get("c").unwrap());
+        probability_model
+            .add_probabilities(&count_model, &smoothing_type)
+            .unwrap();
+        assert_eq!((4.0 / 9.0), probability_model.get("a").unwrap());
+        assert_eq!((2.0 / 9.0), probability_model.get("b").unwrap());
+        assert_eq!((1.0 / 9.0), probability_model.get("c").unwrap());
     }
 
     #[test]
@@ -223,35 +776,35 @@ mod test {
         count_model
             .count_ngrams_from_text_model(&text_model)
             .unwrap();
-        count_model.smooth(&smoothing_type).unwrap();
-        probability_model.add_unigram_probabilities(&count_model).unwrap();
-        probability_model.add_ngram_probabilities(&count_model).unwrap();
-        assert_eq!(&(2.0 / 4.0), probability_model.get("aa").unwrap());
-        assert_eq!(&(1.0 / 4.0), probability_model.get("ab").unwrap());
-        assert_eq!(&(0.0 / 4.0), probability_model.get("ac").unwrap());
-        assert_eq!(&(1.0 / 4.0), probability_model.get("a#").unwrap());
-        assert_eq!(&(0.0 / 2.0), probability_model.get("bb").unwrap());
-        assert_eq!(&(1.0 / 2.0), probability_model.get("ba").unwrap());
-        assert_eq!(&(1.0 / 2.0), probability_model.get("bc").unwrap());
-        assert_eq!(&(0.0 / 2.0), probability_model.get("b#").unwrap());
-        assert_eq!(&(0.0 / 1.0), probability_model.get("ca").unwrap());
-        assert_eq!(&(1.0 / 1.0), probability_model.get("cb").unwrap());
-        assert_eq!(&(0.0 / 1.0), probability_model.get("cc").unwrap());
-        assert_eq!(&(0.0 / 1.0), probability_model.get("c#").unwrap());
-        assert_eq!(&(2.0 / 4.0), probability_model.get("##").unwrap());
-        assert_eq!(&(1.0 / 4.0), probability_model.get("#a").unwrap());
-        assert_eq!(&(0.0 / 4.0), probability_model.get("#b").unwrap());
-        assert_eq!(&(0.0 / 4.0), probability_model.get("#c").unwrap());
+        probability_model
+            .add_probabilities(&count_model, &smoothing_type)
+            .unwrap();
+        assert_eq!((2.0 / 4.0), probability_model.get("aa").unwrap());
+        assert_eq!((1.0 / 4.0), probability_model.get("ab").unwrap());
+        assert_eq!((0.0 / 4.0), probability_model.get("ac").unwrap());
+        assert_eq!((1.0 / 4.0), probability_model.get("a#").unwrap());
+        assert_eq!((0.0 / 2.0), probability_model.get("bb").unwrap());
+        assert_eq!((1.0 / 2.0), probability_model.get("ba").unwrap());
+        assert_eq!((1.0 / 2.0), probability_model.get("bc").unwrap());
+        assert_eq!((0.0 / 2.0), probability_model.get("b#").unwrap());
+        assert_eq!((0.0 / 1.0), probability_model.get("ca").unwrap());
+        assert_eq!((1.0 / 1.0), probability_model.get("cb").unwrap());
+        assert_eq!((0.0 / 1.0), probability_model.get("cc").unwrap());
+        assert_eq!((0.0 / 1.0), probability_model.get("c#").unwrap());
+        assert_eq!((2.0 / 3.0), probability_model.get("##").unwrap());
+        assert_eq!((1.0 / 3.0), probability_model.get("#a").unwrap());
+        assert_eq!((0.0 / 3.0), probability_model.get("#b").unwrap());
+        assert_eq!((0.0 / 3.0), probability_model.get("#c").unwrap());
     }
 
     #[test]
     fn test_probability_model4() {
         let path = String::from("./data/models/test.model");
         let probability_model = ProbabilityModel::from_file(&path[..]).unwrap();
-        assert_eq!(&0.13530510588511946, probability_model.get("a").unwrap());
-        assert_eq!(&0.08394062078272607, probability_model.get("b").unwrap());
-        assert_eq!(&0.13530510588511946, probability_model.get("c").unwrap());
-        assert_eq!(&0.07047140931516639, probability_model.get("#").unwrap());
+        assert_eq!(0.13530510588511946, probability_model.get("a").unwrap());
+        assert_eq!(0.08394062078272607, probability_model.get("b").unwrap());
+        assert_eq!(0.13530510588511946, probability_model.get("c").unwrap());
+        assert_eq!(0.07047140931516639, probability_model.get("#").unwrap());
     }
 
     #[test]
@@ -266,5 +819,278 @@ mod test {
             "test",
             ProbabilityModel::parse_name_from_path(&path2[..]).unwrap()
         );
+        assert_eq!(
+            "pt_BR",
+            ProbabilityModel::parse_name_from_path("/opt/lang/models/pt_BR.model").unwrap()
+        );
+        assert_eq!(
+            "english",
+            ProbabilityModel::parse_name_from_path("english.bin").unwrap()
+        );
+        assert!(ProbabilityModel::parse_name_from_path("/").is_err());
+    }
+
+    #[test]
+    fn test_probability_model6() {
+        let sigma: Sigma = Sigma::new(Some(35), SigmaType::Test);
+        let mut header = ModelHeader::new("header_test", &sigma, 1, &SmoothingType::NoSmoothing);
+        header.symbol_count = 7;
+        let mut probability_model = ProbabilityModel::from_name("header_test").unwrap();
+        probability_model
+            .table_mut()
+            .unwrap()
+            .insert(String::from("#"), 0.25);
+        probability_model.set_header(header.clone());
+        let path = std::env::temp_dir().join("naive_langguesser_header_test.model");
+        let path = path.to_str().unwrap();
+        probability_model.write_to_file(path).unwrap();
+        let loaded = ProbabilityModel::from_file(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!("header_test", loaded.name);
+        assert_eq!(Some(header), loaded.header);
+        assert_eq!(0.25, loaded.get("#").unwrap());
+    }
+
+    #[test]
+    fn test_probability_model7() {
+        let path = std::env::temp_dir().join("naive_langguesser_convert_test.model");
+        let path = path.to_str().unwrap();
+        let probability_model = ProbabilityModel::from_file("./data/models/test.model").unwrap();
+        probability_model
+            .write_to_file_as(path, &ModelFormat::Binary)
+            .unwrap();
+        let converted = ProbabilityModel::from_file(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(Some(0.13530510588511946_f64.log2()), converted.get_log("a"));
+        assert_eq!(Some(0.07047140931516639_f64.log2()), converted.get_log("#"));
+        assert_eq!(None, converted.get_log("d"));
+        assert_eq!(4, converted.entries().len());
+    }
+
+    #[test]
+    fn test_probability_model8() {
+        let raw_text = String::from("ñaña");
+        let ngram_length: usize = 2;
+        let smoothing_type: SmoothingType = SmoothingType::NoSmoothing;
+        let mut sigma: Sigma = Sigma::new(None, SigmaType::Corpus);
+        sigma.extend_from_text(&raw_text[..]);
+        let mut text_model = TextModel::new(ngram_length, &sigma).unwrap();
+        let mut count_model = CountModel::from_sigma(&sigma, ngram_length).unwrap();
+        let mut probability_model = ProbabilityModel::from_name("test").unwrap();
+        text_model.extend(&raw_text[..]);
+        count_model
+            .count_ngrams_from_text_model(&text_model)
+            .unwrap();
+        probability_model
+            .add_probabilities(&count_model, &smoothing_type)
+            .unwrap();
+        assert_eq!(Some(2.0 / 4.0), probability_model.get("ñ"));
+        assert_eq!(Some(2.0 / 2.0), probability_model.get("ña"));
+        assert_eq!(Some(1.0 / 1.0), probability_model.get("añ"));
+        assert_eq!(Some(0.0 / 2.0), probability_model.get("ññ"));
+    }
+
+    #[test]
+    fn test_probability_model9() {
+        let raw_text = String::from("aabcbaa");
+        let ngram_length: usize = 3;
+        let sigma: Sigma = Sigma::new(None, SigmaType::Test);
+        let smoothing_type: SmoothingType = SmoothingType::AddOneSmoothing;
+        let mut text_model = TextModel::new(ngram_length, &sigma).unwrap();
+        let mut count_model = CountModel::from_sigma(&sigma, ngram_length).unwrap();
+        let mut probability_model = ProbabilityModel::from_name("test").unwrap();
+        text_model.extend(&raw_text[..]);
+        count_model
+            .count_ngrams_from_text_model(&text_model)
+            .unwrap();
+        probability_model
+            .add_probabilities(&count_model, &smoothing_type)
+            .unwrap();
+        // trigrams with prefix "ab": abc 1, vocabulary 3
+        assert_eq!(Some(2.0 / 4.0), probability_model.get("abc"));
+        // unseen trigram with seen prefix
+        assert_eq!(Some(1.0 / 4.0), probability_model.get("abb"));
+        // unseen trigram with unseen prefix
+        assert_eq!(Some(1.0 / 3.0), probability_model.get("cca"));
+        assert_eq!(
+            Some((1.0f64 / 3.0).log2()),
+            probability_model.get_log("cca")
+        );
+    }
+
+    #[test]
+    fn test_probability_model10() {
+        let raw_text = String::from("aabcbaa");
+        let ngram_length: usize = 3;
+        let smoothing_type: SmoothingType = SmoothingType::KneserNeySmoothing;
+        let (_, probability_model) = train_test_model(&raw_text, ngram_length, &smoothing_type);
+        // every conditional distribution sums up to one, seen context or not
+        assert_normalized(&probability_model, ngram_length);
+        assert_eq!(
+            Some(probability_model.get("cca").unwrap().log2()),
+            probability_model.get_log("cca")
+        );
+    }
+
+    #[test]
+    fn test_probability_model11() {
+        let raw_text = String::from("aabcbaabaaac");
+        let ngram_length: usize = 3;
+        let smoothing_type: SmoothingType = SmoothingType::GoodTuringSmoothing;
+        let (_, probability_model) = train_test_model(&raw_text, ngram_length, &smoothing_type);
+        // every conditional distribution sums up to one, seen context or not
+        assert_normalized(&probability_model, ngram_length);
+        // unseen continuations of a seen context get a share of its mass
+        assert!(probability_model.get("cc").unwrap() > 0.0);
+        assert_eq!(Some(1.0 / 3.0), probability_model.get("cca"));
+    }
+
+    #[test]
+    fn test_probability_model12() {
+        let raw_text = String::from("aabcbaabaaac");
+        let ngram_length: usize = 3;
+        let smoothing_type = SmoothingType::LidstoneSmoothing(Some(vec![0.5, 0.1]));
+        let (count_model, probability_model) =
+            train_test_model(&raw_text, ngram_length, &smoothing_type);
+        assert_normalized(&probability_model, ngram_length);
+        // |a| = 7 of 12 unigrams, |ab| = 2 of |a*| = 7 bigrams, |ba*| = 2 trigrams
+        let expected: Vec<(&str, f64)> =
+            vec![("a", 7.5 / 13.5), ("ab", 2.1 / 7.3), ("bac", 0.1 / 2.3)];
+        for (ngram, prob) in expected {
+            assert!((probability_model.get(ngram).unwrap() - prob).abs() < 1e-12, "{}", ngram);
+        }
+        let untuned = SmoothingType::LidstoneSmoothing(None);
+        let mut probability_model = ProbabilityModel::from_name("test").unwrap();
+        assert!(probability_model.add_probabilities(&count_model, &untuned).is_err());
+    }
+
+    #[test]
+    fn test_probability_model13() {
+        let raw_text = String::from("aabcbaabaaac");
+        let ngram_length: usize = 3;
+        let smoothing_type: SmoothingType = SmoothingType::AbsoluteDiscounting;
+        let (_, probability_model) = train_test_model(&raw_text, ngram_length, &smoothing_type);
+        assert_normalized(&probability_model, ngram_length);
+        // no unigram seen once, so D = 0.5: P(c) = 1.5 / 12 + 0.5 * 3 / 12 * 1 / 3
+        assert!((probability_model.get("c").unwrap() - 1.0 / 6.0).abs() < 1e-12);
+        // bigrams: D = 3 / (3 + 2 * 2), only cb follows c: P(c|c) = D * 1 / 1 * P(c)
+        assert!((probability_model.get("cc").unwrap() - 3.0 / 7.0 / 6.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_probability_model14() {
+        let path = std::env::temp_dir().join("naive_langguesser_illformed_test.model");
+        let path = path.to_str().unwrap();
+        fs::write(path, "a\t0.5\nb\tlikely\n").unwrap();
+        let err = ProbabilityModel::from_file(path).err().unwrap();
+        assert_eq!(Some(path), err.file());
+        assert_eq!(Some(2), err.line());
+        assert!(err.to_string().starts_with(&format!("{}:2: ", path)));
+        fs::write(path, "#!naive_langguesser model\nname: test\nngram_length: x\n\n").unwrap();
+        let err = ProbabilityModel::from_file(path).err().unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(Some(3), err.line());
+        let err = ProbabilityModel::from_file("./data/models/missing.model").err().unwrap();
+        assert_eq!(Some("./data/models/missing.model"), err.file());
+        assert_eq!(None, err.line());
+        match err {
+            Error::Io { source, .. } => assert_eq!(io::ErrorKind::NotFound, source.kind()),
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_probability_model15() {
+        let raw_text = String::from("aabcbaabaaac");
+        let ngram_length: usize = 3;
+        let (count_model, _) =
+            train_test_model(&raw_text, ngram_length, &SmoothingType::NoSmoothing);
+        let smoothing_types = [
+            SmoothingType::NoSmoothing,
+            SmoothingType::AddOneSmoothing,
+            SmoothingType::WittenBellSmoothing,
+            SmoothingType::LidstoneSmoothing(Some(vec![0.5, 0.1])),
+            SmoothingType::GoodTuringSmoothing,
+            SmoothingType::KneserNeySmoothing,
+            SmoothingType::AbsoluteDiscounting,
+        ];
+        for smoothing_type in smoothing_types.iter() {
+            let mut probability_model = ProbabilityModel::from_name("test").unwrap();
+            probability_model
+                .add_probabilities(&count_model, smoothing_type)
+                .unwrap();
+            assert_eq!(
+                Vec::<(String, f64)>::new(),
+                probability_model.check_normalization(NORMALIZATION_TOLERANCE),
+                "smoothing: {}",
+                smoothing_type.name()
+            );
+        }
+        let mut probability_model = ProbabilityModel::from_name("test").unwrap();
+        probability_model
+            .add_probabilities(&count_model, &SmoothingType::NoSmoothing)
+            .unwrap();
+        probability_model.table_mut().unwrap().insert(String::from("ab"), 0.5);
+        let deviations = probability_model.check_normalization(NORMALIZATION_TOLERANCE);
+        assert_eq!(1, deviations.len());
+        assert_eq!("a", deviations[0].0);
+    }
+
+    #[test]
+    fn test_probability_model16() {
+        let raw_text = String::from("aabcbaabaaac");
+        let ngram_length: usize = 2;
+        let smoothing_type: SmoothingType = SmoothingType::WittenBellSmoothing;
+        let (_, probability_model) = train_test_model(&raw_text, ngram_length, &smoothing_type);
+        // all symbols follow "a", so no probability is left to unseen bigrams after it
+        assert_eq!(None, probability_model.get_exact("a\u{FFFD}"));
+        // only "c" and "a" follow "b": T = 2, |b*| = 3, P = 2 / 5 / (3 - 2)
+        assert_eq!(Some(0.4), probability_model.get_exact("b\u{FFFD}"));
+        assert_eq!(Some(0.4), probability_model.get("bb"));
+    }
+
+    /// Count the ngrams of a text of the `Test` alphabet and smooth them
+    fn train_test_model(
+        raw_text: &str,
+        ngram_length: usize,
+        smoothing_type: &SmoothingType,
+    ) -> (CountModel, ProbabilityModel) {
+        let sigma: Sigma = Sigma::new(None, SigmaType::Test);
+        let mut text_model = TextModel::new(ngram_length, &sigma).unwrap();
+        let mut count_model = CountModel::from_sigma(&sigma, ngram_length).unwrap();
+        let mut probability_model = ProbabilityModel::from_name("test").unwrap();
+        text_model.extend(raw_text);
+        count_model
+            .count_ngrams_from_text_model(&text_model)
+            .unwrap();
+        probability_model
+            .add_probabilities(&count_model, smoothing_type)
+            .unwrap();
+        (count_model, probability_model)
+    }
+
+    /// Assert that the symbols of the `Test` alphabet following any prefix sum up to one
+    fn assert_normalized(probability_model: &ProbabilityModel, ngram_length: usize) {
+        let symbols = ["a", "b", "c"];
+        let mut prefixes: Vec<String> = vec![String::new()];
+        for length in 1..ngram_length {
+            let longer: Vec<String> = prefixes
+                .iter()
+                .filter(|prefix| prefix.len() == length - 1)
+                .flat_map(|prefix| {
+                    symbols
+                        .iter()
+                        .map(move |symbol| format!("{}{}", prefix, symbol))
+                })
+                .collect();
+            prefixes.extend(longer);
+        }
+        for prefix in prefixes.iter() {
+            let sum: f64 = symbols
+                .iter()
+                .map(|symbol| probability_model.get(&format!("{}{}", prefix, symbol)).unwrap())
+                .sum();
+            assert!((sum - 1.0).abs() < 1e-9, "prefix: {}, sum: {}", prefix, sum);
+        }
     }
 }
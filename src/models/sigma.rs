@@ -1,6 +1,8 @@
 use itertools::Itertools;
-use models::symbol::Symbol;
+use errors::Error;
+use models::symbol::{Symbol, UNSEEN_SYMBOL};
 use std::collections::HashSet;
+use std::ops::RangeInclusive;
 
 
 /// Definition of sigma/alphabet types
@@ -9,16 +11,69 @@ use std::collections::HashSet;
 /// 
 /// * `AlphaNum` - consists of lower/capital letters and numbers
 /// * `Ascii` - consists of the set of ascii symbols (without control symbols; so 32-126)
+/// * `Latin` - ascii symbols plus the letters of Latin-1 Supplement and Latin Extended-A/B
+/// * `Cyrillic` - ascii symbols without letters plus the Cyrillic block
+/// * `Greek` - ascii symbols without letters plus the Greek and Coptic block
+/// * `Arabic` - ascii symbols without letters plus the Arabic block
+/// * `Hebrew` - ascii symbols without letters plus the Hebrew block
+/// * `Devanagari` - ascii symbols without letters plus the Devanagari block
+/// * `Cjk` - ascii symbols without letters plus CJK symbols, kana and unified ideographs
+/// * `Corpus` - all symbols (without control symbols) occurring in the training text
 /// * `Test` - just `abc` for internal testing purposes
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum SigmaType {
     AlphaNum,
     Ascii,
+    Latin,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Hebrew,
+    Devanagari,
+    Cjk,
+    Corpus,
     Test,
 }
 
 impl SigmaType {
+    /// Parse sigma type from its name as used on the cli and in model headers
+    pub fn from_name(name: &str) -> Option<SigmaType> {
+        match name {
+            "alphanum" => Some(SigmaType::AlphaNum),
+            "ascii" => Some(SigmaType::Ascii),
+            "latin" => Some(SigmaType::Latin),
+            "cyrillic" => Some(SigmaType::Cyrillic),
+            "greek" => Some(SigmaType::Greek),
+            "arabic" => Some(SigmaType::Arabic),
+            "hebrew" => Some(SigmaType::Hebrew),
+            "devanagari" => Some(SigmaType::Devanagari),
+            "cjk" => Some(SigmaType::Cjk),
+            "corpus" => Some(SigmaType::Corpus),
+            "test" => Some(SigmaType::Test),
+            _ => None,
+        }
+    }
+
+    /// Name of sigma type as used on the cli and in model headers
+    pub fn name(&self) -> &'static str {
+        match self {
+            SigmaType::AlphaNum => "alphanum",
+            SigmaType::Ascii => "ascii",
+            SigmaType::Latin => "latin",
+            SigmaType::Cyrillic => "cyrillic",
+            SigmaType::Greek => "greek",
+            SigmaType::Arabic => "arabic",
+            SigmaType::Hebrew => "hebrew",
+            SigmaType::Devanagari => "devanagari",
+            SigmaType::Cjk => "cjk",
+            SigmaType::Corpus => "corpus",
+            SigmaType::Test => "test",
+        }
+    }
+
     /// provide sigma as Set of Symbols
+    ///
+    /// The `Corpus` sigma is empty, it gets filled from the training text.
     pub fn get_symbols(&self) -> HashSet<Symbol> {
         match self {
             SigmaType::AlphaNum => {
@@ -44,6 +99,26 @@ impl SigmaType {
                 .into_iter()
                 .map(|byte| Symbol::from_u8(byte))
                 .collect(),
+            SigmaType::Latin => {
+                let mut sigma: HashSet<Symbol> = SigmaType::Ascii.get_symbols();
+                // without multiplication (U+00D7) and division sign (U+00F7)
+                sigma.extend(code_point_symbols(0xC0..=0xD6));
+                sigma.extend(code_point_symbols(0xD8..=0xF6));
+                sigma.extend(code_point_symbols(0xF8..=0x24F));
+                sigma
+            }
+            SigmaType::Cyrillic => with_ascii_non_letters(code_point_symbols(0x400..=0x4FF)),
+            SigmaType::Greek => with_ascii_non_letters(code_point_symbols(0x370..=0x3FF)),
+            SigmaType::Arabic => with_ascii_non_letters(code_point_symbols(0x600..=0x6FF)),
+            SigmaType::Hebrew => with_ascii_non_letters(code_point_symbols(0x590..=0x5FF)),
+            SigmaType::Devanagari => with_ascii_non_letters(code_point_symbols(0x900..=0x97F)),
+            SigmaType::Cjk => {
+                // CJK symbols and punctuation, hiragana and katakana
+                let mut symbols: HashSet<Symbol> = code_point_symbols(0x3000..=0x30FF);
+                symbols.extend(code_point_symbols(0x4E00..=0x9FFF));
+                with_ascii_non_letters(symbols)
+            }
+            SigmaType::Corpus => HashSet::new(),
             SigmaType::Test => (97..=99)
                 .into_iter()
                 .map(|byte| Symbol::from_u8(byte))
@@ -52,6 +127,27 @@ impl SigmaType {
     }
 }
 
+/// Collect symbols of a range of unicode code points
+///
+/// Code points which aren't valid chars (e.g. surrogates) and control symbols are skipped.
+fn code_point_symbols(code_points: RangeInclusive<u32>) -> HashSet<Symbol> {
+    code_points
+        .filter_map(std::char::from_u32)
+        .filter(|symbol| !symbol.is_control())
+        .map(Symbol::from_char)
+        .collect()
+}
+
+/// Add space, digits and punctuation of ascii to the symbols of a script
+fn with_ascii_non_letters(mut symbols: HashSet<Symbol>) -> HashSet<Symbol> {
+    symbols.extend(
+        (32..=126_u8)
+            .filter(|byte| !byte.is_ascii_alphabetic())
+            .map(Symbol::from_u8),
+    );
+    symbols
+}
+
 /// Encapsulates sigma relevant information
 ///
 /// # Sigma
@@ -83,6 +179,18 @@ impl Sigma {
         }
     }
 
+    /// Add all symbols of a text to sigma, control symbols are skipped
+    ///
+    /// This builds the `Corpus` sigma from a training text. The `UNSEEN_SYMBOL` is skipped as
+    /// well, as it is reserved for unseen ngrams of sparse models.
+    pub fn extend_from_text(&mut self, text: &str) {
+        let symbols = text
+            .chars()
+            .filter(|symbol| !symbol.is_control() && *symbol != UNSEEN_SYMBOL)
+            .map(Symbol::from_char);
+        self.sigma.extend(symbols);
+    }
+
     /// check if sigma holds symbol
     pub fn contains(&self, symbol: Symbol) -> Option<Symbol> {
         match self.sigma.contains(&symbol) {
@@ -109,11 +217,11 @@ impl Sigma {
             .collect::<Vec<Symbol>>()
     }
 
-    pub fn as_string_vec(&self) -> Vec<String> {
+    pub fn as_string_vec(&self) -> Result<Vec<String>, Error> {
         self.sigma
             .iter()
             .map(|symbol| symbol.as_string())
-            .collect::<Vec<String>>()
+            .collect::<Result<Vec<String>, Error>>()
     }
 }
 
@@ -153,19 +261,19 @@ impl NGramGenerator {
 
 /// Definition for ngram interface
 pub trait NGramExt {
-    fn ngrams(&self, n: usize) -> Vec<String>;
+    fn ngrams(&self, n: usize) -> Result<Vec<String>, Error>;
 }
 
 /// Implementation of ngram interface for sigma
 ///
 /// *Note*: This approach is inspired by Jean VanCoppenolle. See README for more information.
 impl NGramExt for Sigma {
-    fn ngrams(&self, ngram_length: usize) -> Vec<String> {
+    fn ngrams(&self, ngram_length: usize) -> Result<Vec<String>, Error> {
         assert!(ngram_length > 0);
         let generator = NGramGenerator {
-            unigrams: self.as_string_vec(),
+            unigrams: self.as_string_vec()?,
         };
-        generator.generate(ngram_length)
+        Ok(generator.generate(ngram_length))
     }
 }
 
@@ -190,11 +298,11 @@ mod test {
     #[test]
     fn test_get_unigram_features() {
         let sigma: Sigma = Sigma::new(None, SigmaType::Test);
-        let mut ngrams = sigma.ngrams(1);
-        let mut result: Vec<String> = Vec::new();
-        result.push(String::from("a"));
-        result.push(String::from("b"));
-        result.push(String::from("c"));
+        let mut ngrams = sigma.ngrams(1).unwrap();
+        let mut result: Vec<String> = vec!["a", "b", "c"]
+            .into_iter()
+            .map(String::from)
+            .collect();
         ngrams.sort();
         result.sort();
         assert_eq!(result, ngrams);
@@ -203,17 +311,13 @@ mod test {
     #[test]
     fn test_get_bigram_features() {
         let sigma: Sigma = Sigma::new(None, SigmaType::Test);
-        let mut ngrams = sigma.ngrams(2);
-        let mut result: Vec<String> = Vec::new();
-        result.push(String::from("aa"));
-        result.push(String::from("ab"));
-        result.push(String::from("ac"));
-        result.push(String::from("ba"));
-        result.push(String::from("bb"));
-        result.push(String::from("bc"));
-        result.push(String::from("ca"));
-        result.push(String::from("cb"));
-        result.push(String::from("cc"));
+        let mut ngrams = sigma.ngrams(2).unwrap();
+        let mut result: Vec<String> = vec![
+            "aa", "ab", "ac", "ba", "bb", "bc", "ca", "cb", "cc",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
         ngrams.sort();
         result.sort();
         assert_eq!(result, ngrams);
@@ -222,37 +326,43 @@ mod test {
     #[test]
     fn test_get_threegram_features() {
         let sigma: Sigma = Sigma::new(None, SigmaType::Test);
-        let mut ngrams = sigma.ngrams(3);
-        let mut result: Vec<String> = Vec::new();
-        result.push(String::from("aaa"));
-        result.push(String::from("aab"));
-        result.push(String::from("aac"));
-        result.push(String::from("cbc"));
-        result.push(String::from("bcc"));
-        result.push(String::from("bbc"));
-        result.push(String::from("bba"));
-        result.push(String::from("acc"));
-        result.push(String::from("bbb"));
-        result.push(String::from("bab"));
-        result.push(String::from("abb"));
-        result.push(String::from("aca"));
-        result.push(String::from("baa"));
-        result.push(String::from("ccc"));
-        result.push(String::from("cca"));
-        result.push(String::from("cba"));
-        result.push(String::from("ccb"));
-        result.push(String::from("aba"));
-        result.push(String::from("bcb"));
-        result.push(String::from("abc"));
-        result.push(String::from("cac"));
-        result.push(String::from("cbb"));
-        result.push(String::from("caa"));
-        result.push(String::from("bca"));
-        result.push(String::from("cab"));
-        result.push(String::from("bac"));
-        result.push(String::from("acb"));
+        let mut ngrams = sigma.ngrams(3).unwrap();
+        let mut result: Vec<String> = vec![
+            "aaa", "aab", "aac", "cbc", "bcc", "bbc", "bba", "acc", "bbb", "bab", "abb", "aca",
+            "baa", "ccc", "cca", "cba", "ccb", "aba", "bcb", "abc", "cac", "cbb", "caa", "bca",
+            "cab", "bac", "acb",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
         ngrams.sort();
         result.sort();
         assert_eq!(result, ngrams);
     }
+
+    #[test]
+    fn test_unicode_sigma() {
+        let latin: Sigma = Sigma::new(None, SigmaType::Latin);
+        assert!(latin.contains(Symbol::from_str("ü")).is_some());
+        assert!(latin.contains(Symbol::from_str("ñ")).is_some());
+        assert!(latin.contains(Symbol::from_str("a")).is_some());
+        assert!(latin.contains(Symbol::from_str("×")).is_none());
+        let cyrillic: Sigma = Sigma::new(None, SigmaType::Cyrillic);
+        assert!(cyrillic.contains(Symbol::from_str("ж")).is_some());
+        assert!(cyrillic.contains(Symbol::from_str(" ")).is_some());
+        assert!(cyrillic.contains(Symbol::from_str("a")).is_none());
+        let cjk: Sigma = Sigma::new(None, SigmaType::Cjk);
+        assert!(cjk.contains(Symbol::from_str("語")).is_some());
+        assert!(cjk.contains(Symbol::from_str("の")).is_some());
+    }
+
+    #[test]
+    fn test_corpus_sigma() {
+        let mut sigma: Sigma = Sigma::new(Some(35), SigmaType::Corpus);
+        assert_eq!(1, sigma.as_ref().len());
+        sigma.extend_from_text("añ\tña💖");
+        let mut symbols = sigma.as_string_vec().unwrap();
+        symbols.sort();
+        assert_eq!(vec!["#", "a", "ñ", "💖"], symbols);
+    }
 }
@@ -1,5 +1,12 @@
+use errors::Error;
 use std::str;
 
+/// Placeholder symbol for ngrams not held by a sparse probability model
+///
+/// The replacement character isn't part of any alphabet, so e.g. `ab\u{FFFD}` can stand for any
+/// unseen ngram with prefix `ab`.
+pub const UNSEEN_SYMBOL: char = '\u{FFFD}';
+
 /// Deduce the symbols amount of bytes from first byte
 ///
 /// *Note*: This approach is provided by Jean VanCoppenolle. See README for more information.
@@ -24,6 +31,11 @@ impl Symbol {
         }
     }
 
+    pub fn from_char(symbol: char) -> Symbol {
+        let mut buf: [u8; 4] = [0; 4];
+        Symbol::from_str(symbol.encode_utf8(&mut buf))
+    }
+
     pub fn from_u8(byte: u8) -> Symbol {
         let mut symbol: Vec<u8> = Vec::new();
         symbol.push(byte);
@@ -34,15 +46,16 @@ impl Symbol {
         Symbol { symbol }
     }
 
-    pub fn as_str(&self) -> &str {
-        match str::from_utf8(&self.symbol) {
-            Ok(slice) => slice,
-            Err(err) => panic!("Can't cast to &str because of: {}", err),
-        }
+    /// Provide symbol as string slice, which fails if its bytes aren't valid utf-8
+    pub fn as_str(&self) -> Result<&str, Error> {
+        str::from_utf8(&self.symbol).map_err(|err| Error::InvalidSymbol {
+            bytes: self.symbol.clone(),
+            source: err,
+        })
     }
 
-    pub fn as_string(&self) -> String {
-        String::from(self.as_str())
+    pub fn as_string(&self) -> Result<String, Error> {
+        Ok(String::from(self.as_str()?))
     }
 
     pub fn as_bytes_ref(&self) -> &Vec<u8> {
@@ -50,6 +63,28 @@ impl Symbol {
     }
 }
 
+/// Split an ngram into its prefix and its last symbol
+///
+/// The split respects multi-byte symbols, e.g. `"aß"` is split into `("a", "ß")`.
+pub fn split_last_symbol(ngram: &str) -> (&str, &str) {
+    let idx: usize = match ngram.char_indices().last() {
+        Some((idx, _)) => idx,
+        None => 0,
+    };
+    ngram.split_at(idx)
+}
+
+/// Split an ngram into its first symbol and its suffix
+///
+/// The split respects multi-byte symbols, e.g. `"ßa"` is split into `("ß", "a")`.
+pub fn split_first_symbol(ngram: &str) -> (&str, &str) {
+    let idx: usize = match ngram.chars().next() {
+        Some(symbol) => symbol.len_utf8(),
+        None => 0,
+    };
+    ngram.split_at(idx)
+}
+
 /// Iterate the symbols of a byte represented text
 ///
 /// * `idx` - iterator position in text
@@ -70,7 +105,8 @@ impl Iterator for SymbolIterator {
         }
         let byte: u8 = self.text[self.idx];
         let width = char_width(byte);
-        let offset = self.idx + (width);
+        // never read beyond the text, even for truncated multi-byte symbols
+        let offset = usize::min(self.idx + width, self.text_length);
         let symbol_bytes = &self.text[self.idx..offset];
         self.idx = offset;
         let symbol = Symbol::from_vec_of_u8(symbol_bytes.to_vec());
@@ -116,6 +152,17 @@ mod test {
         assert_eq!(ngrams.next(), None);
     }
 
+    #[test]
+    fn test_split_last_symbol() {
+        assert_eq!(("ab", "c"), split_last_symbol("abc"));
+        assert_eq!(("aß", "ñ"), split_last_symbol("aßñ"));
+        assert_eq!(("", "💖"), split_last_symbol("💖"));
+        assert_eq!(("", ""), split_last_symbol(""));
+        assert_eq!(("ñ", "aß"), split_first_symbol("ñaß"));
+        assert_eq!(("", ""), split_first_symbol(""));
+        assert_eq!(Symbol::from_str("ж"), Symbol::from_char('ж'));
+    }
+
     static CORRECT_CHAR_WIDTH: [u8; 256] = [
         1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
         1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
@@ -137,4 +184,11 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_symbol_as_str() {
+        assert_eq!("ß", Symbol::from_str("ß").as_str().unwrap());
+        assert!(Symbol::from_u8(200).as_str().is_err());
+        assert!(Symbol::from_vec_of_u8(vec![0xc3]).as_string().is_err());
+    }
 }
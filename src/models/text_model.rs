@@ -1,7 +1,8 @@
-use models::errors::TextError;
+use errors::Error;
 use models::sigma::Sigma;
 use models::symbol::{Symbol, SymbolExt};
-use std::iter::FromIterator;
+use models::count_model::CountModel;
+use std::collections::VecDeque;
 use std::str;
 
 /// Holding a symbol sequence as addition for the text
@@ -47,22 +48,32 @@ impl Confix {
 /// * `set_confix` - specify addition of confix
 /// * `sigma` - text's alphabet
 /// * `symbols` - symbols of the text
+/// * `spans` - byte offsets (start, end) of the symbols in the text
+/// * `byte_count` - count of bytes of the text, including filtered symbols
 pub struct TextModel {
     set_confix: Option<Confix>,
     sigma: Sigma,
     symbols: Vec<Symbol>,
+    spans: Vec<(usize, usize)>,
+    byte_count: usize,
 }
 
 impl TextModel {
-    pub fn new(ngram_length: usize, sigma: &Sigma) -> Result<TextModel, TextError> {
+    pub fn new(ngram_length: usize, sigma: &Sigma) -> Result<TextModel, Error> {
         let set_confix: Option<Confix> = match &sigma.set_marker {
-            Some(marker_symbol) => Some(Confix::new(marker_symbol, ngram_length)),
+            Some(marker_symbol) => {
+                // ngrams are joined from the symbols' bytes, so the marker has to be valid utf-8
+                marker_symbol.as_str()?;
+                Some(Confix::new(marker_symbol, ngram_length))
+            }
             None => None,
         };
         Ok(TextModel {
             set_confix,
             sigma: sigma.clone(),
             symbols: Vec::new(),
+            spans: Vec::new(),
+            byte_count: 0,
         })
     }
 
@@ -72,12 +83,36 @@ impl TextModel {
     ///
     /// 1. iterate relevant utf-8 symbols
     /// 2. filter symbols not contained in sigma
+    ///
+    /// The byte offsets of the kept symbols are recorded, successive extensions are treated as
+    /// one continuous text.
     pub fn extend(&mut self, text: &str) {
-        let extension = text
-            .get_symbols()
-            .filter_map(|symbol| self.sigma.contains(symbol))
-            .collect::<Vec<Symbol>>();
-        self.symbols.extend(extension);
+        for symbol in text.get_symbols() {
+            let start: usize = self.byte_count;
+            self.byte_count += symbol.as_bytes_ref().len();
+            if let Some(symbol) = self.sigma.contains(symbol) {
+                self.symbols.push(symbol);
+                self.spans.push((start, self.byte_count));
+            }
+        }
+    }
+
+    /// Provide byte offsets (start, end) of the text symbols contained in sigma (confix excluded)
+    pub fn symbol_spans(&self) -> &[(usize, usize)] {
+        &self.spans
+    }
+
+    /// Provide count of confix symbols in front of the text symbols
+    pub fn confix_length(&self) -> usize {
+        match &self.set_confix {
+            Some(confix) => confix.confix.len(),
+            None => 0,
+        }
+    }
+
+    /// Provide count of text symbols contained in sigma (confix excluded)
+    pub fn symbol_count(&self) -> usize {
+        self.symbols.len()
     }
 
     pub fn get_symbols(&self) -> Vec<Symbol> {
@@ -125,12 +160,117 @@ impl Iterator for NGramIterator {
         };
         let ngram_symbols = &self.text[self.idx..offset];
         self.idx += 1;
-        Some(String::from_iter(
-            ngram_symbols
-                .iter()
-                .map(|symbol| symbol.as_str())
-                .collect::<Vec<&str>>(),
-        ))
+        // text symbols stem from a `&str` and the marker is checked by `TextModel::new`
+        let bytes: Vec<u8> = ngram_symbols
+            .iter()
+            .flat_map(|symbol| symbol.as_bytes_ref().iter().cloned())
+            .collect();
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+/// Count the ngrams of a text streamed in chunks
+///
+/// Unlike `TextModel` the text isn't held, only the last symbols needed to build ngrams across
+/// chunk boundaries. The ngrams of all lengths up to `ngram_length` are counted as they pass,
+/// the counts equal the ones of a `TextModel` holding the whole text.
+///
+/// # Fields
+///
+/// * `sigma` - text's alphabet
+/// * `ngram_length` - max length of the counted ngrams
+/// * `marker` - if set, confix symbol added to start and end of the text
+/// * `window` - last symbols of the text, at most `ngram_length`
+/// * `started` - if set, the leading confix has been counted
+/// * `symbol_count` - count of text symbols contained in sigma (confix excluded)
+/// * `max_ngram_count` - count of ngrams of length `ngram_length` counted
+pub struct TextStream {
+    sigma: Sigma,
+    ngram_length: usize,
+    marker: Option<String>,
+    window: VecDeque<String>,
+    started: bool,
+    symbol_count: usize,
+    max_ngram_count: usize,
+}
+
+impl TextStream {
+    pub fn new(ngram_length: usize, sigma: &Sigma) -> Result<TextStream, Error> {
+        let marker: Option<String> = match &sigma.set_marker {
+            Some(marker_symbol) => Some(marker_symbol.as_string()?),
+            None => None,
+        };
+        Ok(TextStream {
+            sigma: sigma.clone(),
+            ngram_length,
+            marker,
+            window: VecDeque::with_capacity(ngram_length),
+            started: false,
+            symbol_count: 0,
+            max_ngram_count: 0,
+        })
+    }
+
+    /// Count the ngrams of the next chunk of text, successive chunks are one continuous text
+    pub fn extend(&mut self, text: &str, count_model: &mut CountModel) -> Result<(), Error> {
+        self.start(count_model)?;
+        for symbol in text.get_symbols() {
+            if let Some(symbol) = self.sigma.contains(symbol) {
+                self.symbol_count += 1;
+                self.push(symbol.as_string()?, count_model)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Count the ngrams of the trailing confix, the text is complete
+    pub fn finish(&mut self, count_model: &mut CountModel) -> Result<(), Error> {
+        self.start(count_model)?;
+        self.push_confix(count_model)
+    }
+
+    /// Provide count of text symbols contained in sigma (confix excluded)
+    pub fn symbol_count(&self) -> usize {
+        self.symbol_count
+    }
+
+    /// Provide count of ngrams of max length counted so far
+    pub fn max_ngram_count(&self) -> usize {
+        self.max_ngram_count
+    }
+
+    fn start(&mut self, count_model: &mut CountModel) -> Result<(), Error> {
+        if !self.started {
+            self.started = true;
+            self.push_confix(count_model)?;
+        }
+        Ok(())
+    }
+
+    fn push_confix(&mut self, count_model: &mut CountModel) -> Result<(), Error> {
+        if let Some(marker) = self.marker.clone() {
+            for _ in 0..self.ngram_length {
+                self.push(marker.clone(), count_model)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Count all ngrams ending with the symbol
+    fn push(&mut self, symbol: String, count_model: &mut CountModel) -> Result<(), Error> {
+        if self.window.len() == self.ngram_length {
+            self.window.pop_front();
+        }
+        self.window.push_back(symbol);
+        let mut ngram: String = String::new();
+        for symbol in self.window.iter().rev() {
+            ngram.insert_str(0, symbol);
+            count_model.count_ngram(&ngram)?;
+        }
+        if self.window.len() == self.ngram_length {
+            self.max_ngram_count += 1;
+        }
+        Ok(())
     }
 }
 
@@ -169,4 +309,49 @@ mod test {
         assert_eq!(Some(String::from("bc")), iter.next());
         assert_eq!(None, iter.next());
     }
+
+    #[test]
+    fn test_text_model_invalid_marker() {
+        let sigma: Sigma = Sigma::new(Some(200), SigmaType::Test);
+        assert!(TextModel::new(2, &sigma).is_err());
+    }
+
+    #[test]
+    fn test_text_model_symbol_spans() {
+        let sigma: Sigma = Sigma::new(Some(35), SigmaType::AlphaNum);
+        let mut text_model = TextModel::new(2, &sigma).unwrap();
+        text_model.extend("añb");
+        text_model.extend(" c");
+        assert_eq!(&[(0, 1), (3, 4), (5, 6)], text_model.symbol_spans());
+        assert_eq!(3, text_model.symbol_count());
+        assert_eq!(2, text_model.confix_length());
+    }
+
+    #[test]
+    fn test_text_stream() {
+        for set_marker in [None, Some(35)].iter() {
+            let sigma: Sigma = Sigma::new(*set_marker, SigmaType::Test);
+            let mut text_model = TextModel::new(3, &sigma).unwrap();
+            text_model.extend("aabcxbaa");
+            let mut expected = CountModel::from_sigma(&sigma, 3).unwrap();
+            expected.count_ngrams_from_text_model(&text_model).unwrap();
+            let mut count_model = CountModel::from_sigma(&sigma, 3).unwrap();
+            let mut stream = TextStream::new(3, &sigma).unwrap();
+            for chunk in ["a", "abcx", "", "baa"].iter() {
+                stream.extend(chunk, &mut count_model).unwrap();
+            }
+            stream.finish(&mut count_model).unwrap();
+            assert_eq!(7, stream.symbol_count());
+            assert_eq!(text_model.ngram_iter(3).count(), stream.max_ngram_count());
+            for idx in 1..=3 {
+                let mut counts: Vec<(&String, &f64)> =
+                    count_model.get_ngram_model(idx).unwrap().iter().collect();
+                let mut expected: Vec<(&String, &f64)> =
+                    expected.get_ngram_model(idx).unwrap().iter().collect();
+                counts.sort_by(|a, b| a.0.cmp(b.0));
+                expected.sort_by(|a, b| a.0.cmp(b.0));
+                assert_eq!(expected, counts);
+            }
+        }
+    }
 }
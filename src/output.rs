@@ -0,0 +1,466 @@
+use inferer::Classification;
+use segmentation::Segment;
+use std::io;
+use std::io::prelude::*;
+
+/// Formats guess results are written in
+///
+/// # Text
+///
+/// Human readable lines, detailed for a single text or a row per text for batches. The detailed
+/// lines read `Guessing <model> with : <log_probability>`, verbose output adds the verdict, the
+/// log likelihood per ngram and the posterior. Ranked batches give a row per text and reported
+/// language model instead.
+///
+/// # Json
+///
+/// A JSON array holding an object per text.
+///
+/// # Jsonl
+///
+/// A JSON object per text and line.
+///
+/// # Csv / Tsv
+///
+/// A header row and a row per text and language model (or per segment), separated by comma or
+/// tab.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Jsonl,
+    Csv,
+    Tsv,
+}
+
+impl OutputFormat {
+    /// Parse output format from its name as used on the cli
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            "jsonl" => Some(OutputFormat::Jsonl),
+            "csv" => Some(OutputFormat::Csv),
+            "tsv" => Some(OutputFormat::Tsv),
+            _ => None,
+        }
+    }
+}
+
+/// Result of guessing the language of a text
+///
+/// # Classified
+///
+/// The text is classified as a whole.
+///
+/// # Segmented
+///
+/// The text is split into segments of different language.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GuessResult {
+    Classified(Classification),
+    Segmented(Vec<Segment>),
+}
+
+/// Write guess results of texts in an output format
+///
+/// Results are written one text at a time, `finish` has to be called after the last text to
+/// complete the output (e.g. close the JSON array).
+///
+/// # Fields
+///
+/// * `out` - destination of the output
+/// * `format` - output format
+/// * `detailed` - write all guesses in `Text` format instead of a row per text
+/// * `verbose` - add verdict, per ngram score and posterior to the detailed `Text` format
+/// * `ranked` - write a row per reported guess instead of a row per text in batch `Text` format
+/// * `written` - count of texts written so far
+pub struct GuessWriter<W: Write> {
+    out: W,
+    format: OutputFormat,
+    detailed: bool,
+    verbose: bool,
+    ranked: bool,
+    written: usize,
+}
+
+impl<W: Write> GuessWriter<W> {
+    pub fn new(out: W, format: OutputFormat, detailed: bool) -> GuessWriter<W> {
+        GuessWriter {
+            out,
+            format,
+            detailed,
+            verbose: false,
+            ranked: false,
+            written: 0,
+        }
+    }
+
+    /// Set whether the detailed `Text` format holds verdict, per ngram score and posterior
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
+    /// Set whether batches in `Text` format hold all reported guesses, e.g. for `--top-k`
+    pub fn set_ranked(&mut self, ranked: bool) {
+        self.ranked = ranked;
+    }
+
+    /// Write guess result of text `input` (e.g. its path)
+    pub fn write(&mut self, input: &str, result: &GuessResult) -> io::Result<()> {
+        let rendered: String = match self.format {
+            OutputFormat::Text => self.render_text(input, result),
+            OutputFormat::Json => {
+                let separator: &str = if self.written == 0 { "[\n" } else { ",\n" };
+                format!("{}{}", separator, render_json(input, result))
+            }
+            OutputFormat::Jsonl => format!("{}\n", render_json(input, result)),
+            OutputFormat::Csv => self.render_table(input, result, ','),
+            OutputFormat::Tsv => self.render_table(input, result, '\t'),
+        };
+        self.out.write_all(rendered.as_bytes())?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Complete the output and flush it
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.format == OutputFormat::Json {
+            let closing: &str = if self.written == 0 { "[]\n" } else { "\n]\n" };
+            self.out.write_all(closing.as_bytes())?;
+        }
+        self.out.flush()
+    }
+
+    /// Flush the results written so far, e.g. when streaming
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    fn render_text(&self, input: &str, result: &GuessResult) -> String {
+        let mut rendered = String::new();
+        match result {
+            GuessResult::Segmented(segments) => {
+                for segment in segments {
+                    rendered.push_str(&match self.detailed {
+                        true => format!(
+                            "Segment {}..{}: {}\n",
+                            segment.start, segment.end, segment.name
+                        ),
+                        false => format!(
+                            "{}\t{}..{}\t{}\n",
+                            input, segment.start, segment.end, segment.name
+                        ),
+                    });
+                }
+            }
+            GuessResult::Classified(classification) if self.detailed && self.verbose => {
+                rendered.push_str(&format!("Guessed language: {}\n", classification.verdict));
+                for guess in classification.guesses.iter() {
+                    rendered.push_str(&format!(
+                        "Guessing {} with : {} (per ngram: {}, posterior: {})\n",
+                        guess.name, guess.log_probability, guess.normalized, guess.posterior
+                    ));
+                }
+            }
+            GuessResult::Classified(classification) if self.detailed => {
+                for guess in classification.guesses.iter() {
+                    rendered.push_str(&format!(
+                        "Guessing {} with : {}\n",
+                        guess.name, guess.log_probability
+                    ));
+                }
+            }
+            GuessResult::Classified(classification) if self.ranked => {
+                for (rank, guess) in classification.guesses.iter().enumerate() {
+                    rendered.push_str(&format!(
+                        "{}\t{}\t{}\t{}\t{}\t{}\n",
+                        input,
+                        classification.verdict,
+                        rank + 1,
+                        guess.name,
+                        guess.normalized,
+                        guess.posterior
+                    ));
+                }
+            }
+            GuessResult::Classified(classification) => {
+                let (normalized, posterior): (f64, f64) = match classification.guesses.first() {
+                    Some(best) => (best.normalized, best.posterior),
+                    None => (0.0, 0.0),
+                };
+                rendered.push_str(&format!(
+                    "{}\t{}\t{}\t{}\n",
+                    input, classification.verdict, normalized, posterior
+                ));
+            }
+        }
+        rendered
+    }
+
+    fn render_table(&self, input: &str, result: &GuessResult, delimiter: char) -> String {
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        match result {
+            GuessResult::Segmented(segments) => {
+                if self.written == 0 {
+                    rows.push(
+                        vec!["input", "start", "end", "model"]
+                            .into_iter()
+                            .map(String::from)
+                            .collect(),
+                    );
+                }
+                for segment in segments {
+                    rows.push(vec![
+                        String::from(input),
+                        segment.start.to_string(),
+                        segment.end.to_string(),
+                        segment.name.clone(),
+                    ]);
+                }
+            }
+            GuessResult::Classified(classification) => {
+                if self.written == 0 {
+                    rows.push(
+                        vec![
+                            "input", "verdict", "rank", "model", "log_probability", "normalized",
+                            "posterior", "ngram_count",
+                        ]
+                        .into_iter()
+                        .map(String::from)
+                        .collect(),
+                    );
+                }
+                for (rank, guess) in classification.guesses.iter().enumerate() {
+                    rows.push(vec![
+                        String::from(input),
+                        classification.verdict.to_string(),
+                        (rank + 1).to_string(),
+                        guess.name.clone(),
+                        guess.log_probability.to_string(),
+                        guess.normalized.to_string(),
+                        guess.posterior.to_string(),
+                        guess.ngram_count.to_string(),
+                    ]);
+                }
+            }
+        }
+        let mut rendered = String::new();
+        for row in rows {
+            let fields: Vec<String> = row
+                .iter()
+                .map(|field| match delimiter {
+                    ',' => escape_csv(field),
+                    _ => escape_tsv(field),
+                })
+                .collect();
+            rendered.push_str(&fields.join(&delimiter.to_string()));
+            rendered.push('\n');
+        }
+        rendered
+    }
+}
+
+fn render_json(input: &str, result: &GuessResult) -> String {
+    match result {
+        GuessResult::Segmented(segments) => {
+            let segments: Vec<String> = segments
+                .iter()
+                .map(|segment| {
+                    format!(
+                        "{{\"start\":{},\"end\":{},\"model\":{}}}",
+                        segment.start,
+                        segment.end,
+                        json_string(&segment.name)
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"input\":{},\"segments\":[{}]}}",
+                json_string(input),
+                segments.join(",")
+            )
+        }
+        GuessResult::Classified(classification) => {
+            let guesses: Vec<String> = classification
+                .guesses
+                .iter()
+                .map(|guess| {
+                    format!(
+                        "{{\"model\":{},\"log_probability\":{},\"normalized\":{},\"posterior\":{},\"ngram_count\":{}}}",
+                        json_string(&guess.name),
+                        json_number(guess.log_probability),
+                        json_number(guess.normalized),
+                        json_number(guess.posterior),
+                        guess.ngram_count
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"input\":{},\"verdict\":{},\"symbol_count\":{},\"guesses\":[{}]}}",
+                json_string(input),
+                json_string(&classification.verdict.to_string()),
+                classification.symbol_count,
+                guesses.join(",")
+            )
+        }
+    }
+}
+
+/// Quote and escape string for JSON
+fn json_string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for symbol in value.chars() {
+        match symbol {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            symbol if (symbol as u32) < 0x20 => {
+                escaped.push_str(&format!("\\u{:04x}", symbol as u32))
+            }
+            symbol => escaped.push(symbol),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Format number for JSON, which has no representation of infinity and NaN
+fn json_number(value: f64) -> String {
+    match value.is_finite() {
+        true => value.to_string(),
+        false => String::from("null"),
+    }
+}
+
+/// Quote CSV field if it contains a delimiter, quote or line break
+fn escape_csv(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        String::from(field)
+    }
+}
+
+/// Escape tabs and line breaks of TSV field
+fn escape_tsv(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use inferer::{Guess, Verdict};
+
+    fn classified() -> GuessResult {
+        GuessResult::Classified(Classification {
+            verdict: Verdict::Language(String::from("english")),
+            guesses: vec![
+                Guess {
+                    name: String::from("english"),
+                    log_probability: -20.0,
+                    normalized: -2.0,
+                    posterior: 0.75,
+                    ngram_count: 10,
+                },
+                Guess {
+                    name: String::from("german"),
+                    log_probability: f64::NEG_INFINITY,
+                    normalized: f64::NEG_INFINITY,
+                    posterior: 0.25,
+                    ngram_count: 10,
+                },
+            ],
+            symbol_count: 12,
+        })
+    }
+
+    fn written(format: OutputFormat, results: &[(&str, GuessResult)]) -> String {
+        let mut buf: Vec<u8> = Vec::new();
+        {
+            let mut writer = GuessWriter::new(&mut buf, format, false);
+            for (input, result) in results {
+                writer.write(input, result).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_output1() {
+        let json = written(OutputFormat::Jsonl, &[("a \"b\".txt", classified())]);
+        assert_eq!(
+            "{\"input\":\"a \\\"b\\\".txt\",\"verdict\":\"english\",\"symbol_count\":12,\"guesses\":[\
+             {\"model\":\"english\",\"log_probability\":-20,\"normalized\":-2,\"posterior\":0.75,\"ngram_count\":10},\
+             {\"model\":\"german\",\"log_probability\":null,\"normalized\":null,\"posterior\":0.25,\"ngram_count\":10}]}\n",
+            json
+        );
+        assert_eq!("[]\n", written(OutputFormat::Json, &[]));
+        let json = written(OutputFormat::Json, &[("a", classified()), ("b", classified())]);
+        assert!(json.starts_with("[\n{\"input\":\"a\""));
+        assert!(json.contains("},\n{\"input\":\"b\""));
+        assert!(json.ends_with("]}\n]\n"));
+    }
+
+    #[test]
+    fn test_output2() {
+        let csv = written(OutputFormat::Csv, &[("a,b.txt", classified())]);
+        assert_eq!(
+            "input,verdict,rank,model,log_probability,normalized,posterior,ngram_count\n\
+             \"a,b.txt\",english,1,english,-20,-2,0.75,10\n\
+             \"a,b.txt\",english,2,german,-inf,-inf,0.25,10\n",
+            csv
+        );
+        let segments = GuessResult::Segmented(vec![Segment {
+            start: 0,
+            end: 4,
+            name: String::from("german"),
+        }]);
+        let tsv = written(OutputFormat::Tsv, &[("a\tb", segments)]);
+        assert_eq!("input\tstart\tend\tmodel\na\\tb\t0\t4\tgerman\n", tsv);
+        let text = written(OutputFormat::Text, &[("a.txt", classified())]);
+        assert_eq!("a.txt\tenglish\t-2\t0.75\n", text);
+        let mut buf: Vec<u8> = Vec::new();
+        {
+            let mut writer = GuessWriter::new(&mut buf, OutputFormat::Text, false);
+            writer.set_ranked(true);
+            writer.write("a.txt", &classified()).unwrap();
+            writer.finish().unwrap();
+        }
+        assert_eq!(
+            "a.txt\tenglish\t1\tenglish\t-2\t0.75\na.txt\tenglish\t2\tgerman\t-inf\t0.25\n",
+            String::from_utf8(buf).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_output3() {
+        let detailed = |verbose: bool| -> String {
+            let mut buf: Vec<u8> = Vec::new();
+            {
+                let mut writer = GuessWriter::new(&mut buf, OutputFormat::Text, true);
+                writer.set_verbose(verbose);
+                writer.write("a.txt", &classified()).unwrap();
+                writer.finish().unwrap();
+            }
+            String::from_utf8(buf).unwrap()
+        };
+        assert_eq!(
+            "Guessing english with : -20\nGuessing german with : -inf\n",
+            detailed(false)
+        );
+        assert_eq!(
+            "Guessed language: english\n\
+             Guessing english with : -20 (per ngram: -2, posterior: 0.75)\n\
+             Guessing german with : -inf (per ngram: -inf, posterior: 0.25)\n",
+            detailed(true)
+        );
+    }
+}
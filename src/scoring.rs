@@ -0,0 +1,299 @@
+use models::probability_model::ProbabilityModel;
+use models::symbol::{split_first_symbol, split_last_symbol, UNSEEN_SYMBOL};
+use std::collections::HashMap;
+
+/// Max count of EM iterations when estimating interpolation weights
+const MAX_EM_ITERATIONS: usize = 50;
+
+/// Change of interpolation weights below which EM estimation is considered converged
+const EM_CONVERGENCE: f64 = 1e-6;
+
+/// Ways to score a text's ngrams against a probability model
+///
+/// # Exact
+///
+/// Look up ngrams of the guessing ngram length only. Unseen ngrams are covered by the unseen
+/// entries of sparse models, an ngram not covered at all is an error.
+///
+/// # Backoff
+///
+/// Katz-style backoff: seen ngrams are scored by their own probability, unseen ngrams by the
+/// probability of the ngram without its first symbol, weighted so that the distribution of
+/// every prefix sums up to one.
+///
+/// # Interpolation
+///
+/// Jelinek-Mercer interpolation: the probabilities of all ngram lengths `1..=ngram_length` are
+/// mixed linearly. The weights (lambdas, shortest ngram length first) are given or, if missing,
+/// taken from the model header (estimated on held-out text) or uniform otherwise.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScoringType {
+    Exact,
+    Backoff,
+    Interpolation(Option<Vec<f64>>),
+}
+
+impl ScoringType {
+    /// Parse scoring type from its name as used on the cli
+    pub fn from_name(name: &str) -> Option<ScoringType> {
+        match name {
+            "exact" => Some(ScoringType::Exact),
+            "backoff" => Some(ScoringType::Backoff),
+            "interpolation" => Some(ScoringType::Interpolation(None)),
+            _ => None,
+        }
+    }
+
+    /// Provide name of scoring type as used on the cli
+    pub fn name(&self) -> &str {
+        match self {
+            ScoringType::Exact => "exact",
+            ScoringType::Backoff => "backoff",
+            ScoringType::Interpolation(_) => "interpolation",
+        }
+    }
+}
+
+/// Katz backoff weights of the prefixes (contexts) of a probability model
+///
+/// The weight of a prefix `h` with the seen ngrams `hw` is
+///
+///  alpha(h) = (1 - sum P(w|h)) / (1 - sum P(w|h'))
+///
+/// with `h'` being `h` without its first symbol. It moves the probability mass left over by the
+/// seen ngrams to the unseen ones. Prefixes without seen ngrams have weight one.
+pub struct BackoffWeights {
+    weights: HashMap<String, f64>,
+}
+
+impl BackoffWeights {
+    /// Calculate weights of the prefixes of ngrams up to `ngram_length`
+    pub fn from_model(model: &ProbabilityModel, ngram_length: usize) -> BackoffWeights {
+        let mut seen: Vec<HashMap<String, Vec<String>>> = vec![HashMap::new(); ngram_length + 1];
+        for (ngram, prob) in model.entries() {
+            let length: usize = ngram.chars().count();
+            if length < 2 || length > ngram_length || prob <= 0.0 || ngram.contains(UNSEEN_SYMBOL)
+            {
+                continue;
+            }
+            let (prefix, _) = split_last_symbol(&ngram);
+            seen[length]
+                .entry(String::from(prefix))
+                .or_default()
+                .push(ngram.clone());
+        }
+        let mut backoff_weights = BackoffWeights {
+            weights: HashMap::new(),
+        };
+        // weights of shorter prefixes are needed for the weights of longer ones
+        for contexts in seen.iter() {
+            let mut weights: Vec<(String, f64)> = Vec::new();
+            for (prefix, ngrams) in contexts.iter() {
+                let mut seen_mass: f64 = 0.0;
+                let mut lower_order_mass: f64 = 0.0;
+                for ngram in ngrams {
+                    seen_mass += model.get_exact(ngram).unwrap_or(0.0);
+                    let (_, suffix) = split_first_symbol(ngram);
+                    lower_order_mass += backoff_weights.probability(model, suffix);
+                }
+                let weight: f64 = if lower_order_mass < 1.0 {
+                    ((1.0 - seen_mass) / (1.0 - lower_order_mass)).max(0.0)
+                } else {
+                    0.0
+                };
+                weights.push((prefix.clone(), weight));
+            }
+            backoff_weights.weights.extend(weights);
+        }
+        backoff_weights
+    }
+
+    /// Provide backoff probability of an ngram
+    ///
+    /// Unigrams are looked up including the unseen entries of sparse models, an unknown unigram
+    /// has probability zero.
+    pub fn probability(&self, model: &ProbabilityModel, ngram: &str) -> f64 {
+        let (prefix, _) = split_last_symbol(ngram);
+        if prefix.is_empty() {
+            return model.get(ngram).unwrap_or(0.0);
+        }
+        match model.get_exact(ngram) {
+            Some(prob) if prob > 0.0 => prob,
+            _ => {
+                let weight: f64 = self.weights.get(prefix).cloned().unwrap_or(1.0);
+                match weight > 0.0 {
+                    true => weight * self.probability(model, split_first_symbol(ngram).1),
+                    false => 0.0,
+                }
+            }
+        }
+    }
+}
+
+/// Provide probabilities of the ngram's suffixes of length `1..=ngram length`
+///
+/// Ngrams missing in the model have probability zero.
+pub fn suffix_probabilities(model: &ProbabilityModel, ngram: &str) -> Vec<f64> {
+    let mut probabilities: Vec<f64> = Vec::new();
+    let mut suffix: &str = ngram;
+    while !suffix.is_empty() {
+        probabilities.push(model.get(suffix).unwrap_or(0.0));
+        suffix = split_first_symbol(suffix).1;
+    }
+    probabilities.reverse();
+    probabilities
+}
+
+/// Provide linear interpolation of the probabilities of an ngram's suffixes
+///
+/// # Arguments
+///
+/// * `model` - probability model to look up ngrams in
+/// * `lambdas` - weights of the ngram lengths, shortest first, summing up to one
+/// * `ngram` - ngram to score
+pub fn interpolated_probability(model: &ProbabilityModel, lambdas: &[f64], ngram: &str) -> f64 {
+    suffix_probabilities(model, ngram)
+        .iter()
+        .zip(lambdas.iter())
+        .map(|(prob, lambda)| prob * lambda)
+        .sum()
+}
+
+/// Provide uniform interpolation weights for ngrams of length `1..=ngram_length`
+pub fn uniform_lambdas(ngram_length: usize) -> Vec<f64> {
+    vec![1.0 / ngram_length as f64; ngram_length]
+}
+
+/// Fit interpolation weights to ngrams of length `ngram_length`
+///
+/// Weights of longer ngrams are dropped, the remaining ones are rescaled to sum up to one.
+/// If no weights are left uniform weights are used.
+pub fn fit_lambdas(lambdas: &[f64], ngram_length: usize) -> Vec<f64> {
+    let fitted: Vec<f64> = lambdas.iter().take(ngram_length).cloned().collect();
+    let sum: f64 = fitted.iter().sum();
+    if fitted.len() < ngram_length || sum <= 0.0 {
+        return uniform_lambdas(ngram_length);
+    }
+    fitted.iter().map(|lambda| lambda / sum).collect()
+}
+
+/// Estimate interpolation weights on held-out ngrams by expectation maximization
+///
+/// Each iteration assigns every held-out ngram to the ngram lengths in proportion to their
+/// weighted probabilities and sets the new weights to the average assignment. Iterations stop
+/// if the weights converge.
+///
+/// # Arguments
+///
+/// * `model` - probability model to look up ngrams in
+/// * `held_out` - ngrams of held-out text, all of length `ngram_length`
+/// * `ngram_length` - length of the held-out ngrams
+pub fn estimate_lambdas(
+    model: &ProbabilityModel,
+    held_out: &[String],
+    ngram_length: usize,
+) -> Vec<f64> {
+    let probabilities: Vec<Vec<f64>> = held_out
+        .iter()
+        .map(|ngram| suffix_probabilities(model, ngram))
+        .filter(|probabilities| probabilities.len() == ngram_length)
+        .collect();
+    let mut lambdas: Vec<f64> = uniform_lambdas(ngram_length);
+    for _ in 0..MAX_EM_ITERATIONS {
+        let mut expected: Vec<f64> = vec![0.0; ngram_length];
+        let mut observed: f64 = 0.0;
+        for probs in probabilities.iter() {
+            let total: f64 = probs.iter().zip(lambdas.iter()).map(|(p, l)| p * l).sum();
+            if total <= 0.0 {
+                continue;
+            }
+            for (idx, (prob, lambda)) in probs.iter().zip(lambdas.iter()).enumerate() {
+                expected[idx] += prob * lambda / total;
+            }
+            observed += 1.0;
+        }
+        if observed == 0.0 {
+            break;
+        }
+        let updated: Vec<f64> = expected.iter().map(|count| count / observed).collect();
+        let change: f64 = updated
+            .iter()
+            .zip(lambdas.iter())
+            .map(|(new, old)| (new - old).abs())
+            .fold(0.0, f64::max);
+        lambdas = updated;
+        if change < EM_CONVERGENCE {
+            break;
+        }
+    }
+    lambdas
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use models::count_model::CountModel;
+    use models::sigma::{Sigma, SigmaType};
+    use models::text_model::TextModel;
+    use smoothing::SmoothingType;
+
+    fn test_model(smoothing_type: &SmoothingType) -> ProbabilityModel {
+        let raw_text = String::from("aabcbaa");
+        let ngram_length: usize = 2;
+        let sigma: Sigma = Sigma::new(None, SigmaType::Test);
+        let mut text_model = TextModel::new(ngram_length, &sigma).unwrap();
+        let mut count_model = CountModel::from_sigma(&sigma, ngram_length).unwrap();
+        let mut probability_model = ProbabilityModel::from_name("test").unwrap();
+        text_model.extend(&raw_text[..]);
+        count_model
+            .count_ngrams_from_text_model(&text_model)
+            .unwrap();
+        probability_model
+            .add_probabilities(&count_model, smoothing_type)
+            .unwrap();
+        probability_model
+    }
+
+    #[test]
+    fn test_scoring1() {
+        let model = test_model(&SmoothingType::AddOneSmoothing);
+        let weights = BackoffWeights::from_model(&model, 2);
+        // distributions of seen and unseen prefixes sum up to one
+        for prefix in ["a", "b", "c"].iter() {
+            let sum: f64 = ["a", "b", "c"]
+                .iter()
+                .map(|symbol| weights.probability(&model, &format!("{}{}", prefix, symbol)))
+                .sum();
+            assert!((sum - 1.0).abs() < 1e-9, "prefix: {}", prefix);
+        }
+        assert_eq!(model.get("ab").unwrap(), weights.probability(&model, "ab"));
+    }
+
+    #[test]
+    fn test_scoring2() {
+        let model = test_model(&SmoothingType::NoSmoothing);
+        let lambdas: Vec<f64> = vec![0.25, 0.75];
+        // P(c) = 1 / 7, P(c|b) = 1 / 2
+        let expected: f64 = 0.25 / 7.0 + 0.75 / 2.0;
+        assert!((expected - interpolated_probability(&model, &lambdas, "bc")).abs() < 1e-12);
+        // unseen bigram is still scored through the unigram
+        assert!(interpolated_probability(&model, &lambdas, "cc") > 0.0);
+        assert_eq!(vec![0.5, 0.5], fit_lambdas(&[1.0], 2));
+        assert_eq!(vec![0.5, 0.5], fit_lambdas(&[0.2, 0.2, 0.6], 2));
+    }
+
+    #[test]
+    fn test_scoring3() {
+        let model = test_model(&SmoothingType::NoSmoothing);
+        // held-out bigrams unseen in training favour unigrams
+        let held_out: Vec<String> = vec!["cc", "cc", "ca", "ab"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let lambdas = estimate_lambdas(&model, &held_out, 2);
+        assert!((lambdas.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(lambdas[0] > lambdas[1]);
+        // no usable held-out ngrams leave the weights uniform
+        assert_eq!(uniform_lambdas(2), estimate_lambdas(&model, &[], 2));
+    }
+}
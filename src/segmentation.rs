@@ -0,0 +1,141 @@
+/// Text segment labeled with a language
+///
+/// # Fields
+///
+/// * `start` - byte offset of the segment's first symbol in the text
+/// * `end` - byte offset behind the segment's last symbol in the text
+/// * `name` - name of the language model labeling the segment
+#[derive(Clone, Debug, PartialEq)]
+pub struct Segment {
+    pub start: usize,
+    pub end: usize,
+    pub name: String,
+}
+
+/// Find the most likely sequence of models for a sequence of ngram scores
+///
+/// Viterbi pass over a hidden markov model with a state per model. Staying with a model is
+/// free, switching to another model costs `switch_penalty` bits, so short runs of ngrams
+/// favouring another model don't split the text.
+///
+/// # Arguments
+///
+/// * `emissions` - log2 probabilities per ngram position, one per model
+/// * `switch_penalty` - cost of switching models in bits
+pub fn viterbi(emissions: &[Vec<f64>], switch_penalty: f64) -> Vec<usize> {
+    let model_count: usize = match emissions.first() {
+        Some(scores) if !scores.is_empty() => scores.len(),
+        _ => return Vec::new(),
+    };
+    let mut scores: Vec<f64> = emissions[0].clone();
+    // backpointers[pos][model]: best model at `pos - 1` leading to `model` at `pos`
+    let mut backpointers: Vec<Vec<usize>> = Vec::with_capacity(emissions.len());
+    backpointers.push((0..model_count).collect());
+    for emission in emissions.iter().skip(1) {
+        let best: usize = argmax(&scores);
+        let mut next_scores: Vec<f64> = Vec::with_capacity(model_count);
+        let mut pointers: Vec<usize> = Vec::with_capacity(model_count);
+        for (model, score) in scores.iter().enumerate() {
+            let switched: f64 = scores[best] - switch_penalty;
+            let (previous, score) = match *score >= switched || best == model {
+                true => (model, *score),
+                false => (best, switched),
+            };
+            next_scores.push(score + emission[model]);
+            pointers.push(previous);
+        }
+        scores = next_scores;
+        backpointers.push(pointers);
+    }
+    let mut path: Vec<usize> = vec![argmax(&scores)];
+    for pointers in backpointers.iter().skip(1).rev() {
+        let previous: usize = pointers[*path.last().expect("path is never empty")];
+        path.push(previous);
+    }
+    path.reverse();
+    path
+}
+
+/// Merge labeled symbols into segments
+///
+/// # Arguments
+///
+/// * `labels` - model index per symbol
+/// * `spans` - byte offsets (start, end) per symbol
+/// * `names` - model names by model index
+pub fn merge_segments(labels: &[usize], spans: &[(usize, usize)], names: &[String]) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut previous: Option<usize> = None;
+    for (label, (start, end)) in labels.iter().zip(spans.iter()) {
+        match segments.last_mut() {
+            Some(segment) if previous == Some(*label) => segment.end = *end,
+            _ => segments.push(Segment {
+                start: *start,
+                end: *end,
+                name: names[*label].clone(),
+            }),
+        }
+        previous = Some(*label);
+    }
+    segments
+}
+
+fn argmax(values: &[f64]) -> usize {
+    let mut best: usize = 0;
+    for (idx, value) in values.iter().enumerate() {
+        if *value > values[best] {
+            best = idx;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_segmentation1() {
+        let a: Vec<f64> = vec![-1.0, -3.0];
+        let b: Vec<f64> = vec![-3.0, -1.0];
+        let emissions: Vec<Vec<f64>> = vec![
+            a.clone(), a.clone(), a.clone(), b.clone(), a.clone(), a.clone(), b.clone(),
+            b.clone(), b.clone(), b.clone(), b.clone(),
+        ];
+        // a single ngram favouring another model doesn't pay the switch
+        assert_eq!(
+            vec![0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1],
+            viterbi(&emissions, 5.0)
+        );
+        // without penalty every ngram follows its best model
+        assert_eq!(
+            vec![0, 0, 0, 1, 0, 0, 1, 1, 1, 1, 1],
+            viterbi(&emissions, 0.0)
+        );
+        // a high penalty keeps the whole text in one model
+        assert_eq!(vec![1; 11], viterbi(&emissions, 100.0));
+        assert_eq!(Vec::<usize>::new(), viterbi(&[], 5.0));
+        assert_eq!(Vec::<usize>::new(), viterbi(&[vec![], vec![]], 5.0));
+    }
+
+    #[test]
+    fn test_segmentation2() {
+        let names: Vec<String> = vec![String::from("english"), String::from("german")];
+        let spans: Vec<(usize, usize)> = vec![(0, 1), (1, 2), (3, 4), (4, 6), (6, 7)];
+        assert_eq!(
+            vec![
+                Segment {
+                    start: 0,
+                    end: 4,
+                    name: String::from("english"),
+                },
+                Segment {
+                    start: 4,
+                    end: 7,
+                    name: String::from("german"),
+                },
+            ],
+            merge_segments(&[0, 0, 0, 1, 1], &spans, &names)
+        );
+    }
+}
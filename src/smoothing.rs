@@ -1,6 +1,16 @@
-use errors::SmoothingError;
+use errors::Error;
 use models::ngram_model::NGramModel;
-use std::collections::HashMap;
+use models::symbol::{split_first_symbol, split_last_symbol};
+use std::collections::{BTreeMap, HashMap};
+
+/// Pseudo-count of Lidstone smoothing for ngram lengths without held-out ngrams to tune it on
+pub const DEFAULT_PSEUDO_COUNT: f64 = 1.0;
+
+/// Range the pseudo-count of Lidstone smoothing is tuned in
+const PSEUDO_COUNT_RANGE: (f64, f64) = (1e-5, 10.0);
+
+/// Count of golden section steps when tuning the pseudo-count of Lidstone smoothing
+const PSEUDO_COUNT_STEPS: usize = 60;
 
 /// Present types of smoothing
 ///
@@ -11,6 +21,9 @@ use std::collections::HashMap;
 /// page 206: Smoothing
 /// ISBN 0-13-095069-6
 ///
+/// All smoothing types estimate the distribution of the symbols following each prefix (context)
+/// of an ngram length, so every conditional distribution sums up to one.
+///
 /// # NoSmoothing
 ///
 /// Don't perform smoothing/ leave counts unchanged
@@ -18,176 +31,758 @@ use std::collections::HashMap;
 /// # AddOneSmoothing
 ///
 /// Add one to every ngram to deal with unseen ngrams.
-/// Adjust population accordingly.
+/// Adjust population of the prefix accordingly.
 ///
 /// # WittenBellSmoothing
 ///
-/// Use the count of ngrams seen once to estimate the count of ngrams not seen.
+/// Use the count of distinct symbols seen after a prefix to estimate the count of the symbols
+/// not seen after it.
+///
+/// # KneserNeySmoothing
+///
+/// Modified Kneser-Ney smoothing: subtract a discount depending on the count (1, 2, 3+) from
+/// every seen ngram and interpolate with the lower order distribution. Lower order distributions
+/// are based on continuation counts, i.e. the count of distinct symbols preceding an ngram.
+/// In contrast to the other types, it operates across all ngram lengths of a count model.
+///
+/// For further information see:
+///
+/// An Empirical Study of Smoothing Techniques for Language Modeling
+/// Stanley F. Chen / Joshua Goodman
+/// Technical Report TR-10-98, Harvard University, 1998
+///
+/// # AbsoluteDiscounting
 ///
+/// Subtract a fixed discount `D` from every seen ngram and redistribute the freed mass
+/// proportionally to the distribution of the ngrams one symbol shorter. `D` is estimated from the
+/// count of ngrams seen once and twice. Like Kneser-Ney smoothing it operates across all ngram
+/// lengths of a count model, but the shorter ngrams keep their raw counts.
+///
+/// For further information see:
+///
+/// On Structuring Probabilistic Dependences in Stochastic Language Modelling
+/// Hermann Ney / Ute Essen / Reinhard Kneser
+/// Computer Speech and Language 8(1), 1994
+///
+/// # LidstoneSmoothing
+///
+/// Add a pseudo-count `k` to every ngram, `k = 1` equals add-one smoothing per prefix. The
+/// pseudo-counts are given per ngram length (shortest first, the last one holds for longer
+/// ngrams) or, if missing, tuned on held-out lines of the training text. Like Kneser-Ney
+/// smoothing it operates on the raw counts when calculating probabilities.
+///
+/// # GoodTuringSmoothing
+///
+/// Simple Good-Turing smoothing: estimate the count of unseen ngrams by the count of ngrams seen
+/// once and discount the counts of seen ngrams based on the counts of ngrams seen once more often.
+/// Sparse count-of-counts are smoothed by a log-linear regression. Like Kneser-Ney smoothing it
+/// operates on the raw counts when calculating probabilities, to normalize them per prefix.
+///
+/// For further information see:
+///
+/// Good-Turing Frequency Estimation Without Tears
+/// William A. Gale / Geoffrey Sampson
+/// Journal of Quantitative Linguistics 2(3), 1995
+///
+#[derive(Clone, Debug, PartialEq)]
 pub enum SmoothingType {
     NoSmoothing,
     AddOneSmoothing,
     WittenBellSmoothing,
+    KneserNeySmoothing,
+    GoodTuringSmoothing,
+    LidstoneSmoothing(Option<Vec<f64>>),
+    AbsoluteDiscounting,
+}
+
+impl SmoothingType {
+    /// Parse smoothing type from its name as used on the cli and in model headers
+    pub fn from_name(name: &str) -> Option<SmoothingType> {
+        match name {
+            "no" => Some(SmoothingType::NoSmoothing),
+            "add_one" => Some(SmoothingType::AddOneSmoothing),
+            "witten_bell" => Some(SmoothingType::WittenBellSmoothing),
+            "kneser_ney" => Some(SmoothingType::KneserNeySmoothing),
+            "good_turing" => Some(SmoothingType::GoodTuringSmoothing),
+            "lidstone" => Some(SmoothingType::LidstoneSmoothing(None)),
+            "absolute_discounting" => Some(SmoothingType::AbsoluteDiscounting),
+            _ => None,
+        }
+    }
+
+    /// Name of smoothing type as used on the cli and in model headers
+    pub fn name(&self) -> &'static str {
+        match self {
+            SmoothingType::NoSmoothing => "no",
+            SmoothingType::AddOneSmoothing => "add_one",
+            SmoothingType::WittenBellSmoothing => "witten_bell",
+            SmoothingType::KneserNeySmoothing => "kneser_ney",
+            SmoothingType::GoodTuringSmoothing => "good_turing",
+            SmoothingType::LidstoneSmoothing(_) => "lidstone",
+            SmoothingType::AbsoluteDiscounting => "absolute_discounting",
+        }
+    }
+
+    /// Check if unseen ngrams are estimated by backing off to shorter ngrams
+    ///
+    /// Otherwise all unseen ngrams with the same prefix share the same probability.
+    pub fn backs_off(&self) -> bool {
+        matches!(
+            self,
+            SmoothingType::KneserNeySmoothing | SmoothingType::AbsoluteDiscounting
+        )
+    }
 }
 
-/// Performs a redistribution of ngram counts to fill unseen ngrams
-pub fn smoothing(
-    ngram_model: &mut NGramModel,
-    type_: &SmoothingType,
-) -> Result<(), SmoothingError> {
-    match type_ {
-        SmoothingType::NoSmoothing => Ok(()),
-        SmoothingType::AddOneSmoothing => add_one_to_ngram_model(ngram_model),
-        SmoothingType::WittenBellSmoothing => witten_bell_on_ngram_model(ngram_model),
-    }
-}
-
-/// Add one to every ngram to deal with unseen ngrams
-fn add_one_to_ngram_model(ngram_model: &mut NGramModel) -> Result<(), SmoothingError> {
-    let total: f64 = ngram_model.get_total_ngram_count();
-    let vocabulary_size: f64 = ngram_model.get_vocabulary_size() as f64;
-    let model: &mut HashMap<String, f64> = ngram_model.get_mut_model();
-    add_one_to_hashmap(model, total, vocabulary_size)?;
-    Ok(())
-}
-
-fn add_one_to_hashmap(
-    model: &mut HashMap<String, f64>,
-    total: f64,
-    vocabulary_size: f64,
-) -> Result<(), SmoothingError> {
-    let normalization_term: f64 = total / (total + vocabulary_size);
-    for (_ngram, count) in model.iter_mut() {
-        *count = (*count + 1.0) * normalization_term;
-    }
-    Ok(())
-}
-
-/// Use count ngrams seen once to estimate count of unseen ngrams
-fn witten_bell_on_ngram_model(ngram_model: &mut NGramModel) -> Result<(), SmoothingError> {
-    let total: f64 = ngram_model.get_total_ngram_count();
-    let seen: f64 = ngram_model.get_seen_type_count() as f64;
-    let unseen: f64 = ngram_model.get_unseen_type_count() as f64;
-    let model: &mut HashMap<String, f64> = ngram_model.get_mut_model();
-    witten_bell_on_hashmap(model, total, seen, unseen)?;
-    Ok(())
-}
-
-fn witten_bell_on_hashmap(
-    model: &mut HashMap<String, f64>,
-    total: f64,
-    seen: f64,
-    unseen: f64,
-) -> Result<(), SmoothingError> {
-    if seen == 0.0 || unseen == 0.0 {
-        return Ok(());
+/// Calculate the conditional probability of an ngram `hw` seen `count` times after prefix `h`
+///
+/// Covers the smoothing types based on the counts of one ngram length, with `|h*|` being the
+/// sum of counts of ngrams with prefix `h`, `T(h)` the count of those seen and `V` the size of
+/// sigma:
+///
+///  no:           P(w|h) = |hw| / |h*|
+///  add_one:      P(w|h) = (|hw| + 1) / (|h*| + V)
+///  lidstone:     P(w|h) = (|hw| + k) / (|h*| + k * V)
+///  witten_bell:  P(w|h) = |hw| / (|h*| + T(h)) if seen, T(h) / ((|h*| + T(h)) * (V - T(h))) else
+///
+/// Witten-Bell smoothing keeps the unsmoothed distribution if all symbols were seen after `h`.
+/// Ngrams with an unseen prefix get the uniform probability over sigma, unsmoothed ones zero.
+///
+/// # Arguments
+///
+/// * `smoothing_type` - smoothing of the counts
+/// * `ngram_length` - length of the ngram, selects the pseudo-count of lidstone smoothing
+/// * `count` - count of the ngram `hw`
+/// * `context` - statistics of the ngrams with prefix `h`
+/// * `sigma_size` - size of sigma
+pub fn conditional_probability(
+    smoothing_type: &SmoothingType,
+    ngram_length: usize,
+    count: f64,
+    context: &ContextStatistics,
+    sigma_size: f64,
+) -> Result<f64, Error> {
+    let add_k = |k: f64| -> f64 {
+        match context.total + k * sigma_size > 0.0 {
+            true => (count + k) / (context.total + k * sigma_size),
+            false => 1.0 / sigma_size,
+        }
     };
-    let normalization_term: f64 = total / (total + seen);
-    let smoothed_unseen: f64 = (seen / unseen) * normalization_term;
-    for (_ngram, count) in model.iter_mut() {
-        if *count > 0.0 {
-            *count = *count * normalization_term;
+    match smoothing_type {
+        SmoothingType::NoSmoothing => Ok(match context.total > 0.0 {
+            true => count / context.total,
+            false => 0.0,
+        }),
+        SmoothingType::AddOneSmoothing => Ok(add_k(1.0)),
+        SmoothingType::LidstoneSmoothing(Some(pseudo_counts)) => {
+            Ok(add_k(pseudo_count(pseudo_counts, ngram_length)))
+        }
+        SmoothingType::LidstoneSmoothing(None) => Err(Error::model(
+            "Pseudo-counts of lidstone smoothing have to be tuned before",
+        )),
+        SmoothingType::WittenBellSmoothing => {
+            let seen: f64 = context.seen();
+            let unseen: f64 = sigma_size - seen;
+            Ok(if context.total <= 0.0 {
+                1.0 / sigma_size
+            } else if unseen <= 0.0 {
+                count / context.total
+            } else if count > 0.0 {
+                count / (context.total + seen)
+            } else {
+                seen / ((context.total + seen) * unseen)
+            })
+        }
+        _ => Err(Error::model(&format!(
+            "Smoothing type {} isn't calculated per ngram length",
+            smoothing_type.name()
+        ))),
+    }
+}
+
+/// Provide pseudo-count of Lidstone smoothing for ngrams of the given length
+///
+/// The pseudo-counts are given shortest ngram length first, the last one holds for all longer
+/// ngrams.
+pub fn pseudo_count(pseudo_counts: &[f64], ngram_length: usize) -> f64 {
+    match pseudo_counts.get(ngram_length - 1).or_else(|| pseudo_counts.last()) {
+        Some(pseudo_count) => *pseudo_count,
+        None => DEFAULT_PSEUDO_COUNT,
+    }
+}
+
+/// Tune the pseudo-count of Lidstone smoothing for one ngram length on held-out counts
+///
+/// The held-out text is part of the counted text, so the training counts are `counts` minus
+/// `held_out`. With `V` being the size of sigma, the pseudo-count `k` maximizing the held-out
+/// log-likelihood
+///
+///  sum |hw|_held_out * log((|hw|_train + k) / (|h*|_train + k * V))
+///
+/// is searched by golden section within `PSEUDO_COUNT_RANGE`. Ngrams with a prefix unseen in
+/// training are ignored, as they get the uniform probability whatever `k`. Without held-out
+/// ngrams `DEFAULT_PSEUDO_COUNT` is used.
+pub fn tune_pseudo_count(counts: &NGramModel, held_out: &NGramModel, sigma_size: usize) -> f64 {
+    let mut contexts: HashMap<&str, f64> = HashMap::new();
+    for (ngram, count) in counts.iter() {
+        let (prefix, _) = split_last_symbol(ngram);
+        *contexts.entry(prefix).or_insert(0.0) += count - held_out.count(ngram);
+    }
+    // (held-out count, training count, training count of the prefix)
+    let observations: Vec<(f64, f64, f64)> = held_out
+        .iter()
+        .filter(|(_, count)| **count > 0.0)
+        .filter_map(|(ngram, count)| {
+            let (prefix, _) = split_last_symbol(ngram);
+            match contexts.get(prefix) {
+                Some(total) if *total > 0.0 => Some((*count, counts.count(ngram) - count, *total)),
+                _ => None,
+            }
+        })
+        .collect();
+    if observations.is_empty() {
+        return DEFAULT_PSEUDO_COUNT;
+    }
+    let sigma_size: f64 = sigma_size as f64;
+    let log_likelihood = |log_k: f64| -> f64 {
+        let k: f64 = log_k.exp();
+        observations
+            .iter()
+            .map(|(held_out, count, total)| held_out * ((count + k).ln() - (total + k * sigma_size).ln()))
+            .sum()
+    };
+    let ratio: f64 = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = (PSEUDO_COUNT_RANGE.0.ln(), PSEUDO_COUNT_RANGE.1.ln());
+    for _ in 0..PSEUDO_COUNT_STEPS {
+        let left: f64 = high - ratio * (high - low);
+        let right: f64 = low + ratio * (high - low);
+        if log_likelihood(left) < log_likelihood(right) {
+            low = left;
         } else {
-            *count = smoothed_unseen;
+            high = right;
         }
     }
-    Ok(())
+    ((low + high) / 2.0).exp()
+}
+
+/// Count ngrams per occurence count (frequency of frequencies)
+///
+/// Maps every count `r` of a seen ngram to the count `n_r` of ngrams seen `r` times. Counts are
+/// rounded, as they are only fractional after smoothing.
+pub fn frequency_of_frequencies(ngram_model: &NGramModel) -> BTreeMap<u64, f64> {
+    let mut frequencies: BTreeMap<u64, f64> = BTreeMap::new();
+    for (_, count) in ngram_model.iter() {
+        let count = count.round();
+        if count >= 1.0 {
+            *frequencies.entry(count as u64).or_insert(0.0) += 1.0;
+        }
+    }
+    frequencies
+}
+
+/// Simple Good-Turing estimates of the counts of an ngram model
+///
+/// # Fields
+///
+/// * `unseen_mass` - share of the total count left to all unseen ngrams together
+/// * `counts` - smoothed count `r*` per count `r`, so that the seen ngrams share the rest
+#[derive(Debug, PartialEq)]
+pub struct GoodTuringEstimates {
+    pub unseen_mass: f64,
+    pub counts: BTreeMap<u64, f64>,
+}
+
+impl GoodTuringEstimates {
+    /// Estimate smoothed counts from the frequency of frequencies of an ngram model
+    ///
+    /// With `n_r` being the count of ngrams seen `r` times and `N` the sum of all counts:
+    ///
+    ///  P_0 = n_1 / N
+    ///  Z_r = n_r / (0.5 * (t - q))  with q, t being the next lower and higher count seen
+    ///  log(Z_r) = a + b * log(r)    fitted by least squares
+    ///  x_r = (r + 1) * n_r+1 / n_r  Turing estimate
+    ///  y_r = (r + 1) * ((r + 1) / r)^b
+    ///
+    /// `r*` is the Turing estimate as long as it differs significantly from the regression
+    /// estimate (more than 1.96 times its standard deviation), from then on the regression
+    /// estimate. Finally `r*` is normalized to share `1 - P_0` of the total count, but never
+    /// exceeds `r - P_0`. So every seen ngram gives at least `P_0` of its count to unseen ngrams
+    /// and no prefix leaves them without probability, even if its ngrams are all frequent.
+    ///
+    /// If count-of-counts are too sparse for a regression with slope `b < -1`, the Turing
+    /// estimate is used where `n_r+1` is known and the unsmoothed count otherwise. If all ngrams
+    /// were seen once, `P_0` falls back to the Witten-Bell estimate `T / (N + T)`.
+    pub fn from_ngram_model(ngram_model: &NGramModel) -> GoodTuringEstimates {
+        let frequencies = frequency_of_frequencies(ngram_model);
+        let total: f64 = frequencies.iter().map(|(r, n_r)| *r as f64 * n_r).sum();
+        let seen: f64 = frequencies.values().sum();
+        let singletons: f64 = frequencies.get(&1).cloned().unwrap_or(0.0);
+        let unseen_mass: f64 = if ngram_model.get_unseen_type_count() == 0 || total == 0.0 {
+            0.0
+        } else if singletons < total {
+            singletons / total
+        } else {
+            seen / (total + seen)
+        };
+        let slope: Option<f64> = log_linear_slope(&frequencies).filter(|b| *b < -1.0);
+        let mut counts: BTreeMap<u64, f64> = BTreeMap::new();
+        let mut use_turing: bool = true;
+        for (r, n_r) in frequencies.iter() {
+            let r_f: f64 = *r as f64;
+            let turing: Option<f64> = frequencies
+                .get(&(r + 1))
+                .map(|n_next| (r_f + 1.0) * n_next / n_r);
+            let regression: Option<f64> = slope.map(|b| (r_f + 1.0) * ((r_f + 1.0) / r_f).powf(b));
+            let estimate: f64 = match (turing, regression) {
+                (Some(x), Some(y)) if use_turing => {
+                    let n_next: f64 = frequencies[&(r + 1)];
+                    let deviation: f64 = ((r_f + 1.0).powi(2) * n_next / n_r.powi(2)
+                        * (1.0 + n_next / n_r))
+                        .sqrt();
+                    if (x - y).abs() > 1.96 * deviation {
+                        x
+                    } else {
+                        use_turing = false;
+                        y
+                    }
+                }
+                (_, Some(y)) => {
+                    use_turing = false;
+                    y
+                }
+                (Some(x), None) => x,
+                (None, None) => r_f,
+            };
+            counts.insert(*r, estimate);
+        }
+        let smoothed_total: f64 = counts.iter().map(|(r, r_star)| frequencies[r] * r_star).sum();
+        let normalization_term: f64 = (1.0 - unseen_mass) * total / smoothed_total;
+        for (r, r_star) in counts.iter_mut() {
+            *r_star = (*r_star * normalization_term).min(*r as f64 - unseen_mass);
+        }
+        GoodTuringEstimates {
+            unseen_mass,
+            counts,
+        }
+    }
+
+    /// Provide smoothed count of an ngram seen `count` times
+    pub fn count(&self, count: f64) -> f64 {
+        match self.counts.get(&(count.round() as u64)) {
+            Some(r_star) => *r_star,
+            None => count,
+        }
+    }
+}
+
+/// Fit `log(Z_r) = a + b * log(r)` by least squares and provide slope `b`
+///
+/// `Z_r` averages `n_r` over the gap to the neighbouring counts, see `GoodTuringEstimates`.
+/// At least two distinct counts are required.
+fn log_linear_slope(frequencies: &BTreeMap<u64, f64>) -> Option<f64> {
+    if frequencies.len() < 2 {
+        return None;
+    }
+    let counts: Vec<(f64, f64)> = frequencies.iter().map(|(r, n_r)| (*r as f64, *n_r)).collect();
+    let points: Vec<(f64, f64)> = counts
+        .iter()
+        .enumerate()
+        .map(|(idx, (r, n_r))| {
+            let q: f64 = if idx == 0 { 0.0 } else { counts[idx - 1].0 };
+            let t: f64 = match counts.get(idx + 1) {
+                Some((next, _)) => *next,
+                None => 2.0 * r - q,
+            };
+            (r.ln(), (n_r / (0.5 * (t - q))).ln())
+        })
+        .collect();
+    let length: f64 = points.len() as f64;
+    let mean_x: f64 = points.iter().map(|(x, _)| x).sum::<f64>() / length;
+    let mean_y: f64 = points.iter().map(|(_, y)| y).sum::<f64>() / length;
+    let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let slope: f64 = covariance / variance;
+    match slope.is_finite() {
+        true => Some(slope),
+        false => None,
+    }
+}
+
+/// Discounts of modified Kneser-Ney smoothing for ngrams seen once, twice and more often
+///
+/// # Fields
+///
+/// * `one` - discount for ngrams seen once
+/// * `two` - discount for ngrams seen twice
+/// * `three_plus` - discount for ngrams seen three times or more
+#[derive(Debug, PartialEq)]
+pub struct Discounts {
+    pub one: f64,
+    pub two: f64,
+    pub three_plus: f64,
+}
+
+impl Discounts {
+    /// Estimate discounts from count-of-counts of an ngram model
+    ///
+    /// With `n_r` being the count of ngrams seen `r` times:
+    ///
+    ///  Y = n_1 / (n_1 + 2 * n_2)
+    ///  D_1 = 1 - 2 * Y * n_2 / n_1
+    ///  D_2 = 2 - 3 * Y * n_3 / n_2
+    ///  D_3+ = 3 - 4 * Y * n_4 / n_3
+    ///
+    /// If count-of-counts are too sparse for an estimate, the discount falls back to `Y` or
+    /// `0.5` if `Y` can't be estimated either.
+    pub fn from_ngram_model(ngram_model: &NGramModel) -> Discounts {
+        let mut count_of_counts: [f64; 5] = [0.0; 5];
+        for (_, count) in ngram_model.iter() {
+            let idx = count.round() as usize;
+            if idx > 0 && idx < count_of_counts.len() {
+                count_of_counts[idx] += 1.0;
+            }
+        }
+        let [_, n1, n2, n3, n4] = count_of_counts;
+        let y: f64 = n1 / (n1 + 2.0 * n2);
+        let fallback: f64 = if y.is_finite() && y > 0.0 { y } else { 0.5 };
+        let estimate = |discount: f64, max: f64| -> f64 {
+            if discount.is_finite() && discount > 0.0 && discount <= max {
+                discount
+            } else {
+                fallback
+            }
+        };
+        Discounts {
+            one: estimate(1.0 - 2.0 * y * n2 / n1, 1.0),
+            two: estimate(2.0 - 3.0 * y * n3 / n2, 2.0),
+            three_plus: estimate(3.0 - 4.0 * y * n4 / n3, 3.0),
+        }
+    }
+
+    /// Estimate the single discount of absolute discounting from count-of-counts
+    ///
+    /// With `n_r` being the count of ngrams seen `r` times, all ngrams seen at least once get
+    /// the discount
+    ///
+    ///  D = n_1 / (n_1 + 2 * n_2)
+    ///
+    /// If count-of-counts are too sparse for an estimate, the discount falls back to `0.5`.
+    pub fn absolute(ngram_model: &NGramModel) -> Discounts {
+        let frequencies = frequency_of_frequencies(ngram_model);
+        let n1: f64 = frequencies.get(&1).cloned().unwrap_or(0.0);
+        let n2: f64 = frequencies.get(&2).cloned().unwrap_or(0.0);
+        let discount: f64 = n1 / (n1 + 2.0 * n2);
+        let discount: f64 = if discount.is_finite() && discount > 0.0 && discount < 1.0 {
+            discount
+        } else {
+            0.5
+        };
+        Discounts {
+            one: discount,
+            two: discount,
+            three_plus: discount,
+        }
+    }
+
+    /// Provide discount for an ngram count
+    pub fn get(&self, count: f64) -> f64 {
+        if count >= 3.0 {
+            self.three_plus
+        } else if count >= 2.0 {
+            self.two
+        } else if count >= 1.0 {
+            self.one
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Statistics of the ngrams sharing a prefix (context)
+///
+/// # Fields
+///
+/// * `total` - sum of counts of ngrams with the prefix
+/// * `ones` - count of ngrams with the prefix seen once
+/// * `twos` - count of ngrams with the prefix seen twice
+/// * `three_plus` - count of ngrams with the prefix seen three times or more
+#[derive(Debug, Default, PartialEq)]
+pub struct ContextStatistics {
+    pub total: f64,
+    pub ones: f64,
+    pub twos: f64,
+    pub three_plus: f64,
+}
+
+impl ContextStatistics {
+    /// Provide count of distinct ngrams seen with the prefix
+    pub fn seen(&self) -> f64 {
+        self.ones + self.twos + self.three_plus
+    }
+
+    /// Provide the discounted mass of the context, i.e. the sum of the discounts of its ngrams
+    pub fn discounted_mass(&self, discounts: &Discounts) -> f64 {
+        discounts.one * self.ones + discounts.two * self.twos + discounts.three_plus * self.three_plus
+    }
+}
+
+/// Collect statistics of the ngrams per prefix of an ngram model
+pub fn context_statistics(ngram_model: &NGramModel) -> HashMap<String, ContextStatistics> {
+    let mut contexts: HashMap<String, ContextStatistics> = HashMap::new();
+    for (ngram, count) in ngram_model.iter() {
+        if *count <= 0.0 {
+            continue;
+        }
+        let (prefix, _) = split_last_symbol(ngram);
+        let statistics = contexts.entry(String::from(prefix)).or_default();
+        statistics.total += count;
+        if *count >= 3.0 {
+            statistics.three_plus += 1.0;
+        } else if *count >= 2.0 {
+            statistics.twos += 1.0;
+        } else {
+            statistics.ones += 1.0;
+        }
+    }
+    contexts
+}
+
+/// Derive continuation counts of ngrams one symbol shorter than the ones of `ngram_model`
+///
+/// The continuation count of an ngram is the count of distinct symbols preceding it,
+/// e.g. the continuation count of `bc` is the count of seen ngrams `?bc`.
+pub fn continuation_counts(ngram_model: &NGramModel, vocabulary_size: usize) -> NGramModel {
+    let mut continuation_model = NGramModel::from_vocabulary_size(vocabulary_size);
+    for (ngram, count) in ngram_model.iter() {
+        if *count > 0.0 {
+            let (_, suffix) = split_first_symbol(ngram);
+            continuation_model
+                .add_ngram(suffix)
+                .expect("Sparse ngram models accept every ngram");
+        }
+    }
+    continuation_model
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    fn context(total: f64, ones: f64, twos: f64) -> ContextStatistics {
+        ContextStatistics {
+            total,
+            ones,
+            twos,
+            three_plus: 0.0,
+        }
+    }
+
     #[test]
     fn test_witten_bell_smoothing1() {
-        let mut model: HashMap<String, f64> = HashMap::new();
-        model.insert("a".to_string(), 2.0);
-        model.insert("b".to_string(), 2.0);
-        model.insert("c".to_string(), 0.0);
+        // a: 2, b: 2, c: 0
+        let context = context(4.0, 0.0, 2.0);
+        let prob = |count: f64| {
+            conditional_probability(&SmoothingType::WittenBellSmoothing, 2, count, &context, 3.0)
+                .unwrap()
+        };
+        assert_eq!(2.0 / 6.0, prob(2.0));
+        assert_eq!(2.0 / 6.0, prob(0.0));
+    }
 
-        let total: f64 = 4.0;
-        let seen: f64 = 2.0;
-        let unseen: f64 = 1.0;
+    #[test]
+    fn test_witten_bell_smoothing2() {
+        // a: 1, b: 1, c: 1
+        let context = context(3.0, 3.0, 0.0);
+        let prob = |count: f64| {
+            conditional_probability(&SmoothingType::WittenBellSmoothing, 2, count, &context, 3.0)
+                .unwrap()
+        };
+        assert_eq!(1.0 / 3.0, prob(1.0));
+    }
 
-        witten_bell_on_hashmap(&mut model, total, seen, unseen).unwrap();
-        assert_eq!(&1.3333333333333333, model.get("a").unwrap());
-        assert_eq!(&1.3333333333333333, model.get("b").unwrap());
-        assert_eq!(&1.3333333333333333, model.get("c").unwrap());
+    #[test]
+    fn test_witten_bell_smoothing3() {
+        // a: 0, b: 0, c: 0
+        let context = ContextStatistics::default();
+        let prob = |count: f64| {
+            conditional_probability(&SmoothingType::WittenBellSmoothing, 2, count, &context, 3.0)
+                .unwrap()
+        };
+        assert_eq!(1.0 / 3.0, prob(0.0));
+        let smoothing_type = SmoothingType::NoSmoothing;
+        assert_eq!(0.0, conditional_probability(&smoothing_type, 2, 0.0, &context, 3.0).unwrap());
     }
 
     #[test]
-    fn test_witten_bell_smoothing2() {
-        let mut model: HashMap<String, f64> = HashMap::new();
-        model.insert("a".to_string(), 1.0);
-        model.insert("b".to_string(), 1.0);
-        model.insert("c".to_string(), 1.0);
+    fn test_add_one_smoothing1() {
+        // a: 2, b: 2, c: 0
+        let context = context(4.0, 0.0, 2.0);
+        let prob = |count: f64| {
+            conditional_probability(&SmoothingType::AddOneSmoothing, 2, count, &context, 3.0)
+                .unwrap()
+        };
+        assert_eq!(3.0 / 7.0, prob(2.0));
+        assert_eq!(1.0 / 7.0, prob(0.0));
+    }
 
-        let total: f64 = 3.0;
-        let seen: f64 = 3.0;
-        let unseen: f64 = 0.0;
+    #[test]
+    fn test_add_one_smoothing2() {
+        // a: 0, b: 0, c: 0
+        let context = ContextStatistics::default();
+        let prob = |count: f64| {
+            conditional_probability(&SmoothingType::AddOneSmoothing, 2, count, &context, 3.0)
+                .unwrap()
+        };
+        assert_eq!(1.0 / 3.0, prob(0.0));
+        let lidstone = SmoothingType::LidstoneSmoothing(Some(vec![0.5]));
+        assert_eq!(1.0 / 3.0, conditional_probability(&lidstone, 2, 0.0, &context, 3.0).unwrap());
+    }
 
-        witten_bell_on_hashmap(&mut model, total, seen, unseen).unwrap();
-        assert_eq!(&1.0000000000000000, model.get("a").unwrap());
-        assert_eq!(&1.0000000000000000, model.get("b").unwrap());
-        assert_eq!(&1.0000000000000000, model.get("c").unwrap());
+    #[test]
+    fn test_add_one_smoothing3() {
+        // a: 1, b: 1, c: 1
+        let context = context(3.0, 3.0, 0.0);
+        let prob = |count: f64| {
+            conditional_probability(&SmoothingType::AddOneSmoothing, 2, count, &context, 3.0)
+                .unwrap()
+        };
+        assert_eq!(2.0 / 6.0, prob(1.0));
+        let lidstone = SmoothingType::LidstoneSmoothing(None);
+        assert!(conditional_probability(&lidstone, 2, 1.0, &context, 3.0).is_err());
     }
 
     #[test]
-    fn test_witten_bell_smoothing3() {
-        let mut model: HashMap<String, f64> = HashMap::new();
-        model.insert("a".to_string(), 0.0);
-        model.insert("b".to_string(), 0.0);
-        model.insert("c".to_string(), 0.0);
+    fn test_kneser_ney_discounts1() {
+        let mut model = NGramModel::from_vocabulary_size(26);
+        let counts: Vec<(&str, usize)> = vec![
+            ("a", 1), ("b", 1), ("c", 1), ("d", 1), ("e", 2), ("f", 2), ("g", 3), ("h", 4),
+        ];
+        for (ngram, count) in counts {
+            for _ in 0..count {
+                model.add_ngram(ngram).unwrap();
+            }
+        }
+        // Y = 4 / (4 + 2 * 2) = 0.5
+        let discounts = Discounts::from_ngram_model(&model);
+        assert_eq!(
+            Discounts {
+                one: 0.5,
+                two: 1.25,
+                three_plus: 1.0,
+            },
+            discounts
+        );
+        assert_eq!(0.0, discounts.get(0.0));
+        assert_eq!(1.25, discounts.get(2.0));
+        assert_eq!(1.0, discounts.get(7.0));
+    }
 
-        let total: f64 = 0.0;
-        let seen: f64 = 0.0;
-        let unseen: f64 = 3.0;
+    #[test]
+    fn test_good_turing_smoothing1() {
+        // n_r = 36 / r^2, so Z_r lies exactly on a line of slope -2
+        let mut model = NGramModel::from_vocabulary_size(60);
+        let frequencies: Vec<(usize, usize)> = vec![(1, 36), (2, 9), (3, 4)];
+        let mut idx: usize = 0;
+        for (count, ngrams) in frequencies {
+            for _ in 0..ngrams {
+                model.add_count(&format!("{:02}", idx), count as f64).unwrap();
+                idx += 1;
+            }
+        }
+        let frequencies: Vec<(u64, f64)> = frequency_of_frequencies(&model).into_iter().collect();
+        assert_eq!(vec![(1, 36.0), (2, 9.0), (3, 4.0)], frequencies);
+        let estimates = GoodTuringEstimates::from_ngram_model(&model);
+        assert_eq!(36.0 / 66.0, estimates.unseen_mass);
+        // r* = r^2 / (r + 1), scaled by (1 - P_0) * N / N' = 30 / 39
+        let expected: Vec<f64> = vec![0.5, 4.0 / 3.0, 2.25];
+        for (r, r_star) in expected.iter().enumerate() {
+            assert!((estimates.count((r + 1) as f64) - r_star * 30.0 / 39.0).abs() < 1e-9);
+        }
+    }
 
-        witten_bell_on_hashmap(&mut model, total, seen, unseen).unwrap();
-        assert_eq!(&0.0000000000000000, model.get("a").unwrap());
-        assert_eq!(&0.0000000000000000, model.get("b").unwrap());
-        assert_eq!(&0.0000000000000000, model.get("c").unwrap());
+    #[test]
+    fn test_good_turing_smoothing2() {
+        // a single bin of singletons allows no regression
+        let mut model = NGramModel::from_vocabulary_size(6);
+        for ngram in ["a", "b", "c"].iter() {
+            model.add_ngram(ngram).unwrap();
+        }
+        let estimates = GoodTuringEstimates::from_ngram_model(&model);
+        assert_eq!(0.5, estimates.unseen_mass);
+        assert_eq!(0.5, estimates.count(1.0));
+        // all ngrams seen leave nothing to redistribute, Turing estimates never raise a count
+        let mut model = NGramModel::from_vocabulary_size(2);
+        for ngram in ["a", "a", "b"].iter() {
+            model.add_ngram(ngram).unwrap();
+        }
+        let estimates = GoodTuringEstimates::from_ngram_model(&model);
+        assert_eq!(0.0, estimates.unseen_mass);
+        assert_eq!(1.0, estimates.count(1.0));
+        assert_eq!(1.5, estimates.count(2.0));
     }
 
     #[test]
-    fn test_add_one_smoothing1() {
-        let mut model: HashMap<String, f64> = HashMap::new();
-        model.insert("a".to_string(), 2.0);
-        model.insert("b".to_string(), 2.0);
-        model.insert("c".to_string(), 0.0);
-        let total: f64 = 4.0;
-        let vocabulary_size: f64 = 3.0;
-        add_one_to_hashmap(&mut model, total, vocabulary_size).unwrap();
-        assert_eq!(&1.7142857142857142, model.get("a").unwrap());
-        assert_eq!(&1.7142857142857142, model.get("b").unwrap());
-        assert_eq!(&0.5714285714285714, model.get("c").unwrap());
+    fn test_lidstone_smoothing1() {
+        assert_eq!(0.5, pseudo_count(&[0.5], 3));
+        assert_eq!(0.1, pseudo_count(&[1.0, 0.1, 0.01], 2));
+        assert_eq!(DEFAULT_PSEUDO_COUNT, pseudo_count(&[], 1));
+        // held-out ngrams follow the training distribution of context a closely
+        let mut counts = NGramModel::from_vocabulary_size(9);
+        let mut held_out = NGramModel::from_vocabulary_size(9);
+        for (ngram, count, held_out_count) in [("aa", 60.0, 6.0), ("ab", 40.0, 4.0)].iter() {
+            counts.add_count(ngram, *count).unwrap();
+            held_out.add_count(ngram, *held_out_count).unwrap();
+        }
+        let k: f64 = tune_pseudo_count(&counts, &held_out, 3);
+        assert!(k < 1e-3, "k: {}", k);
+        // held-out ngrams unseen in training call for a large pseudo-count
+        held_out.add_count("ac", 10.0).unwrap();
+        counts.add_count("ac", 10.0).unwrap();
+        let k: f64 = tune_pseudo_count(&counts, &held_out, 3);
+        assert!(k > 1.0, "k: {}", k);
+        let empty = NGramModel::from_vocabulary_size(9);
+        assert_eq!(DEFAULT_PSEUDO_COUNT, tune_pseudo_count(&counts, &empty, 3));
     }
 
     #[test]
-    fn test_add_one_smoothing2() {
-        let mut model: HashMap<String, f64> = HashMap::new();
-        model.insert("a".to_string(), 0.0);
-        model.insert("b".to_string(), 0.0);
-        model.insert("c".to_string(), 0.0);
-        let total: f64 = 0.0;
-        let vocabulary_size: f64 = 3.0;
-        add_one_to_hashmap(&mut model, total, vocabulary_size).unwrap();
-        assert_eq!(&0.0000000000000000, model.get("a").unwrap());
-        assert_eq!(&0.0000000000000000, model.get("b").unwrap());
-        assert_eq!(&0.0000000000000000, model.get("c").unwrap());
+    fn test_absolute_discounts1() {
+        let mut model = NGramModel::from_vocabulary_size(26);
+        for (ngram, count) in [("a", 1.0), ("b", 1.0), ("c", 1.0), ("d", 2.0), ("e", 5.0)].iter() {
+            model.add_count(ngram, *count).unwrap();
+        }
+        // D = 3 / (3 + 2 * 1)
+        let discounts = Discounts::absolute(&model);
+        assert_eq!(0.6, discounts.get(1.0));
+        assert_eq!(0.6, discounts.get(5.0));
+        assert_eq!(0.0, discounts.get(0.0));
+        // only ngrams seen once leave D = 1, which would discard all counts
+        let mut model = NGramModel::from_vocabulary_size(26);
+        model.add_ngram("a").unwrap();
+        assert_eq!(0.5, Discounts::absolute(&model).one);
     }
 
     #[test]
-    fn test_add_one_smoothing3() {
-        let mut model: HashMap<String, f64> = HashMap::new();
-        model.insert("a".to_string(), 1.0);
-        model.insert("b".to_string(), 1.0);
-        model.insert("c".to_string(), 1.0);
-        let total: f64 = 3.0;
-        let vocabulary_size: f64 = 3.0;
-        add_one_to_hashmap(&mut model, total, vocabulary_size).unwrap();
-        assert_eq!(&1.0000000000000000, model.get("a").unwrap());
-        assert_eq!(&1.0000000000000000, model.get("b").unwrap());
-        assert_eq!(&1.0000000000000000, model.get("c").unwrap());
+    fn test_kneser_ney_statistics1() {
+        let mut model = NGramModel::from_vocabulary_size(9);
+        for ngram in ["aa", "aa", "ab", "ba", "ca", "cb", "cb", "cb"].iter() {
+            model.add_ngram(ngram).unwrap();
+        }
+        let contexts = context_statistics(&model);
+        assert_eq!(
+            &ContextStatistics {
+                total: 3.0,
+                ones: 1.0,
+                twos: 1.0,
+                three_plus: 0.0,
+            },
+            contexts.get("a").unwrap()
+        );
+        assert_eq!(4.0, contexts.get("c").unwrap().total);
+        assert_eq!(1.0, contexts.get("b").unwrap().ones);
+        let continuation = continuation_counts(&model, 3);
+        // a is preceded by a, b and c, b by a and c
+        assert_eq!(3.0, continuation.count("a"));
+        assert_eq!(2.0, continuation.count("b"));
+        assert_eq!(0.0, continuation.count("c"));
     }
 }
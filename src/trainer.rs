@@ -0,0 +1,497 @@
+use errors::Error;
+use models::count_model::CountModel;
+use models::model_header::ModelHeader;
+use models::probability_model::ProbabilityModel;
+use models::sigma::{Sigma, SigmaType};
+use models::text_model::{TextModel, TextStream};
+use scoring::estimate_lambdas;
+use smoothing::{tune_pseudo_count, SmoothingType};
+use std::io::Read;
+use utils::{read_chunks, CHUNK_SIZE};
+
+/// Every n-th line of the corpora is held out to tune the pseudo-counts of lidstone smoothing
+pub const TUNING_LINE_INTERVAL: usize = 10;
+
+/// Statistics of a corpus counted into a model
+///
+/// # Fields
+///
+/// * `bytes` - size of the corpus in bytes
+/// * `symbols` - count of corpus symbols contained in the alphabet
+/// * `ngrams` - count of ngrams of max length counted
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorpusStats {
+    pub bytes: usize,
+    pub symbols: usize,
+    pub ngrams: usize,
+}
+
+/// Build probability models from texts held in memory
+///
+/// Nothing is read from or written to disk, the caller decides what to do with the model.
+/// Corpora are either passed all at once to `train`, or one after another to `add_corpus`
+/// followed by `finish`, so only one corpus has to be held in memory at a time.
+///
+/// # Fields
+///
+/// * `name` - name of the language model
+/// * `sigma` - alphabet the model is based on, `Corpus` alphabets are extended by the corpora
+/// * `ngram_length` - max ngram length of the model
+/// * `smoothing_type` - smoothing of the ngram counts
+/// * `held_out` - if set, held-out text to estimate interpolation weights on
+/// * `count_model` - ngram counts of the corpora added so far, set by the first corpus
+/// * `base_counts` - counts of a former training to start from, see `from_counts`
+/// * `header` - header of the model, summing up the corpora added so far
+/// * `held_out_lines` - lines held out to tune pseudo-counts, if lidstone smoothing lacks them
+pub struct ModelTrainer {
+    name: String,
+    sigma: Sigma,
+    ngram_length: usize,
+    smoothing_type: SmoothingType,
+    held_out: Option<String>,
+    count_model: Option<CountModel>,
+    base_counts: Vec<(String, f64)>,
+    header: ModelHeader,
+    held_out_lines: Option<HeldOutLines>,
+}
+
+impl ModelTrainer {
+    pub fn new(
+        name: &str,
+        sigma: Sigma,
+        ngram_length: usize,
+        smoothing_type: SmoothingType,
+    ) -> ModelTrainer {
+        let header = ModelHeader::new(name, &sigma, ngram_length, &smoothing_type);
+        ModelTrainer {
+            name: String::from(name),
+            sigma,
+            ngram_length,
+            smoothing_type,
+            held_out: None,
+            count_model: None,
+            base_counts: Vec::new(),
+            header,
+            held_out_lines: None,
+        }
+    }
+
+    /// Continue a former training from its raw counts, see `CountModel::read_counts`
+    ///
+    /// Name, alphabet, ngram length, smoothing and corpus statistics are taken from the header.
+    /// A `Corpus` alphabet is restored from the counted unigrams and can still be extended.
+    pub fn from_counts(
+        header: ModelHeader,
+        counts: Vec<(String, f64)>,
+    ) -> Result<ModelTrainer, Error> {
+        let mut sigma: Sigma = Sigma::new(header.set_marker, header.sigma_type.clone());
+        if sigma.sigma_type == SigmaType::Corpus {
+            for (ngram, _) in counts.iter().filter(|(ngram, _)| ngram.chars().count() == 1) {
+                sigma.extend_from_text(ngram);
+            }
+        }
+        Ok(ModelTrainer {
+            name: header.name.clone(),
+            sigma,
+            ngram_length: header.ngram_length,
+            smoothing_type: header.smoothing_type.clone(),
+            held_out: None,
+            count_model: None,
+            base_counts: counts,
+            header,
+            held_out_lines: None,
+        })
+    }
+
+    /// Set smoothing of the ngram counts, e.g. to change the smoothing of a former training
+    pub fn set_smoothing_type(&mut self, smoothing_type: SmoothingType) {
+        self.header.smoothing_type = smoothing_type.clone();
+        self.smoothing_type = smoothing_type;
+    }
+
+    /// Set held-out text of the language to estimate interpolation weights on
+    ///
+    /// The weights are stored in the model header and used for interpolation scoring.
+    pub fn set_held_out(&mut self, held_out: &str) {
+        self.held_out = Some(String::from(held_out));
+    }
+
+    /// Extend a `Corpus` alphabet with the symbols of a corpus, other alphabets are left unchanged
+    ///
+    /// All corpora have to extend the alphabet before the first corpus is added, as the counts
+    /// are based on the alphabet.
+    pub fn extend_sigma(&mut self, corpus: &str) -> Result<(), Error> {
+        if self.sigma.sigma_type != SigmaType::Corpus {
+            return Ok(());
+        }
+        if self.count_model.is_some() {
+            return Err(Error::model(
+                "Alphabet can't be extended after the first corpus was added",
+            ));
+        }
+        self.sigma.extend_from_text(corpus);
+        Ok(())
+    }
+
+    /// Count the ngrams of a corpus of the language, no ngram spans two corpora
+    pub fn add_corpus(&mut self, corpus: &str) -> Result<CorpusStats, Error> {
+        self.add_reader(corpus.as_bytes(), CHUNK_SIZE)
+    }
+
+    /// Count the ngrams of a corpus streamed in chunks of about `chunk_size` bytes
+    ///
+    /// Only the counts are kept, so memory depends on the size of the model, not of the corpus.
+    /// If the pseudo-counts of lidstone smoothing are to be tuned, the ngrams of every
+    /// `TUNING_LINE_INTERVAL`th line are counted on their own as well.
+    pub fn add_reader<R: Read>(&mut self, reader: R, chunk_size: usize) -> Result<CorpusStats, Error> {
+        let mut held_out_lines: Option<HeldOutLines> = match self.held_out_lines.take() {
+            Some(held_out_lines) => Some(held_out_lines),
+            None if self.smoothing_type == SmoothingType::LidstoneSmoothing(None) => {
+                Some(HeldOutLines::new(&self.sigma, self.ngram_length)?)
+            }
+            None => None,
+        };
+        let mut stream = TextStream::new(self.ngram_length, &self.sigma)?;
+        let count_model: &mut CountModel = self.count_model()?;
+        let mut bytes: usize = 0;
+        read_chunks(reader, chunk_size, |chunk| {
+            bytes += chunk.len();
+            if let Some(held_out_lines) = &mut held_out_lines {
+                held_out_lines.extend(chunk)?;
+            }
+            stream.extend(chunk, count_model)
+        })?;
+        stream.finish(count_model)?;
+        self.held_out_lines = held_out_lines.map(HeldOutLines::finish);
+        let stats = CorpusStats {
+            bytes,
+            symbols: stream.symbol_count(),
+            ngrams: stream.max_ngram_count(),
+        };
+        self.header.corpus_size += stats.bytes;
+        self.header.symbol_count += stats.symbols;
+        Ok(stats)
+    }
+
+    /// Write raw counts of the corpora added so far, to continue the training later
+    ///
+    /// Written after `finish`, the header holds the tuned pseudo-counts and the estimated
+    /// interpolation weights as well, so a continued training keeps them.
+    pub fn write_counts(&mut self, path: &str) -> Result<(), Error> {
+        let mut header: ModelHeader = self.header.clone();
+        header.sigma_size = self.sigma.sigma.len();
+        self.count_model()?.write_to_file(path, &header)
+    }
+
+    /// Build probability model from the corpora added so far
+    ///
+    /// Pseudo-counts of lidstone smoothing and interpolation weights are tuned and kept in the
+    /// header of the trainer, the counts are kept to be written by `write_counts`.
+    pub fn finish(&mut self) -> Result<ProbabilityModel, Error> {
+        if self.smoothing_type == SmoothingType::LidstoneSmoothing(None) {
+            let held_out_lines = match self.held_out_lines.take() {
+                Some(held_out_lines) => held_out_lines,
+                None => HeldOutLines::new(&self.sigma, self.ngram_length)?,
+            };
+            let pseudo_counts: Vec<f64> = held_out_lines.tune_pseudo_counts(self.count_model()?)?;
+            self.set_smoothing_type(SmoothingType::LidstoneSmoothing(Some(pseudo_counts)));
+        }
+        self.header.sigma_size = self.sigma.sigma.len();
+        let smoothing_type: SmoothingType = self.smoothing_type.clone();
+        let mut probability_model = ProbabilityModel::from_name(&self.name)?;
+        probability_model.add_probabilities(self.count_model()?, &smoothing_type)?;
+        if let Some(held_out) = &self.held_out {
+            let mut held_out_model = TextModel::new(self.ngram_length, &self.sigma)?;
+            held_out_model.extend(held_out);
+            let ngrams: Vec<String> = held_out_model.ngram_iter(self.ngram_length).collect();
+            self.header.interpolation_weights = Some(estimate_lambdas(
+                &probability_model,
+                &ngrams,
+                self.ngram_length,
+            ));
+        }
+        probability_model.set_header(self.header.clone());
+        Ok(probability_model)
+    }
+
+    /// Provide counts of the corpora added so far, set up on first use
+    ///
+    /// The alphabet is fixed from then on, counts of a former training are added on set up.
+    fn count_model(&mut self) -> Result<&mut CountModel, Error> {
+        let count_model: CountModel = match self.count_model.take() {
+            Some(count_model) => count_model,
+            None => {
+                let count_model = self.new_count_model()?;
+                self.base_counts = Vec::new();
+                count_model
+            }
+        };
+        Ok(self.count_model.insert(count_model))
+    }
+
+    fn new_count_model(&self) -> Result<CountModel, Error> {
+        let mut count_model = CountModel::from_sigma(&self.sigma, self.ngram_length)?;
+        for (ngram, count) in &self.base_counts {
+            count_model.add_count(ngram, *count)?;
+        }
+        Ok(count_model)
+    }
+
+    /// Build probability model from one or many corpora of the language
+    ///
+    /// The ngrams of all corpora are counted together, but no ngram spans two corpora.
+    pub fn train(&self, corpora: &[&str]) -> Result<ProbabilityModel, Error> {
+        let mut trainer = ModelTrainer::new(
+            &self.name,
+            self.sigma.clone(),
+            self.ngram_length,
+            self.smoothing_type.clone(),
+        );
+        trainer.held_out = self.held_out.clone();
+        for corpus in corpora {
+            trainer.extend_sigma(corpus)?;
+        }
+        for corpus in corpora {
+            trainer.add_corpus(corpus)?;
+        }
+        trainer.finish()
+    }
+}
+
+/// Lines of the corpora held out to tune the pseudo-counts of lidstone smoothing
+///
+/// Every `TUNING_LINE_INTERVAL`th line of a corpus is held out. Its ngrams are counted on their
+/// own and without confix, so they are part of the counts of the whole corpus as well.
+///
+/// # Fields
+///
+/// * `sigma` - alphabet of the corpora, the marker is dropped
+/// * `ngram_length` - max length of the counted ngrams
+/// * `counts` - ngram counts of the held-out lines
+/// * `line` - index of the current line of the corpus
+/// * `stream` - if the current line is held out, counting its ngrams
+struct HeldOutLines {
+    sigma: Sigma,
+    ngram_length: usize,
+    counts: CountModel,
+    line: usize,
+    stream: Option<TextStream>,
+}
+
+impl HeldOutLines {
+    fn new(sigma: &Sigma, ngram_length: usize) -> Result<HeldOutLines, Error> {
+        let mut sigma: Sigma = sigma.clone();
+        sigma.set_marker = None;
+        let counts = CountModel::from_sigma(&sigma, ngram_length)?;
+        Ok(HeldOutLines {
+            sigma,
+            ngram_length,
+            counts,
+            line: 0,
+            stream: None,
+        })
+    }
+
+    /// Count the ngrams of the held-out lines of the next chunk of the corpus
+    fn extend(&mut self, chunk: &str) -> Result<(), Error> {
+        for (idx, part) in chunk.split('\n').enumerate() {
+            if idx > 0 {
+                self.line += 1;
+                self.stream = None;
+            }
+            if self.line % TUNING_LINE_INTERVAL == TUNING_LINE_INTERVAL - 1 {
+                if self.stream.is_none() {
+                    self.stream = Some(TextStream::new(self.ngram_length, &self.sigma)?);
+                }
+                if let Some(stream) = &mut self.stream {
+                    stream.extend(part, &mut self.counts)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Close the corpus, the next one starts with its first line
+    fn finish(mut self) -> HeldOutLines {
+        self.line = 0;
+        self.stream = None;
+        self
+    }
+
+    /// Tune pseudo-counts of all ngram lengths, see `smoothing::tune_pseudo_count`
+    fn tune_pseudo_counts(&self, count_model: &CountModel) -> Result<Vec<f64>, Error> {
+        let sigma_size: usize = self.sigma.sigma.len();
+        (1..=self.ngram_length)
+            .map(|ngram_length| {
+                match (
+                    count_model.get_ngram_model(ngram_length),
+                    self.counts.get_ngram_model(ngram_length),
+                ) {
+                    (Some(counts), Some(held_out)) => {
+                        Ok(tune_pseudo_count(counts, held_out, sigma_size))
+                    }
+                    _ => Err(Error::model(&format!(
+                        "No count model found for ngram length: {}",
+                        ngram_length
+                    ))),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_trainer1() {
+        let sigma: Sigma = Sigma::new(None, SigmaType::Test);
+        let trainer = ModelTrainer::new("test", sigma, 2, SmoothingType::NoSmoothing);
+        let model = trainer.train(&["aab", "ba"]).unwrap();
+        assert_eq!(Some(0.5), model.get("ab"));
+        assert_eq!(Some(1.0), model.get("ba"));
+        // no bigram "bb" spans the corpora
+        assert_eq!(Some(0.0), model.get("bb"));
+        let header = model.header.as_ref().unwrap();
+        assert_eq!(5, header.corpus_size);
+        assert_eq!(5, header.symbol_count);
+        assert_eq!(None, header.interpolation_weights);
+        let mut trainer = trainer;
+        trainer.set_held_out("abab");
+        let model = trainer.train(&["aab", "ba"]).unwrap();
+        assert!(model.header.unwrap().interpolation_weights.is_some());
+    }
+
+    #[test]
+    fn test_trainer2() {
+        let sigma: Sigma = Sigma::new(None, SigmaType::Test);
+        let mut trainer = ModelTrainer::new("test", sigma.clone(), 2, SmoothingType::NoSmoothing);
+        let stats = trainer.add_corpus("aab").unwrap();
+        assert_eq!(
+            CorpusStats {
+                bytes: 3,
+                symbols: 3,
+                ngrams: 2,
+            },
+            stats
+        );
+        trainer.add_corpus("ba").unwrap();
+        let streamed = trainer.finish().unwrap();
+        let trained = ModelTrainer::new("test", sigma, 2, SmoothingType::NoSmoothing)
+            .train(&["aab", "ba"])
+            .unwrap();
+        for ngram in ["a", "b", "c", "ab", "ba", "bb", "aa"].iter() {
+            assert_eq!(trained.get(ngram), streamed.get(ngram));
+        }
+        assert_eq!(trained.header, streamed.header);
+        // a corpus alphabet is fixed once counting started
+        let corpus: Sigma = Sigma::new(None, SigmaType::Corpus);
+        let mut trainer = ModelTrainer::new("test", corpus, 2, SmoothingType::NoSmoothing);
+        trainer.extend_sigma("ab").unwrap();
+        trainer.add_corpus("ab").unwrap();
+        assert!(trainer.extend_sigma("cd").is_err());
+    }
+
+    #[test]
+    fn test_trainer3() {
+        let path = std::env::temp_dir().join("naive_langguesser_trainer_test.counts");
+        let path = path.to_str().unwrap();
+        let sigma: Sigma = Sigma::new(Some(35), SigmaType::Corpus);
+        let smoothing_type = SmoothingType::WittenBellSmoothing;
+        let mut trainer = ModelTrainer::new("test", sigma.clone(), 2, smoothing_type.clone());
+        trainer.extend_sigma("aab").unwrap();
+        trainer.add_corpus("aab").unwrap();
+        trainer.write_counts(path).unwrap();
+        let (header, counts) = CountModel::read_counts(path).unwrap();
+        fs::remove_file(path).unwrap();
+        let count = |ngram: &str| counts.iter().find(|(counted, _)| counted == ngram).map(|c| c.1);
+        assert_eq!(Some(1.0), count("aa"));
+        assert_eq!(Some(2.0), count("##"));
+        let mut updated = ModelTrainer::from_counts(header, counts).unwrap();
+        // symbols of the additional corpus extend the restored alphabet
+        updated.extend_sigma("bc").unwrap();
+        updated.add_corpus("bc").unwrap();
+        let updated = updated.finish().unwrap();
+        let trained = ModelTrainer::new("test", sigma.clone(), 2, smoothing_type.clone())
+            .train(&["aab", "bc"])
+            .unwrap();
+        for ngram in ["a", "b", "c", "#", "ab", "bc", "cb", "c#", "#a"].iter() {
+            assert_eq!(trained.get(ngram), updated.get(ngram));
+        }
+        assert_eq!(trained.header, updated.header);
+        // weights estimated on held-out text are written with the counts after finishing
+        let mut trainer = ModelTrainer::new("test", sigma, 2, smoothing_type);
+        trainer.set_held_out("abab");
+        trainer.extend_sigma("aab").unwrap();
+        trainer.add_corpus("aab").unwrap();
+        let weights = trainer.finish().unwrap().header.unwrap().interpolation_weights;
+        assert!(weights.is_some());
+        trainer.write_counts(path).unwrap();
+        let (header, counts) = CountModel::read_counts(path).unwrap();
+        assert_eq!(weights, header.interpolation_weights);
+        // an update without held-out text keeps them, in the model and in its counts
+        let mut updated = ModelTrainer::from_counts(header, counts).unwrap();
+        updated.extend_sigma("bc").unwrap();
+        updated.add_corpus("bc").unwrap();
+        let model = updated.finish().unwrap();
+        updated.write_counts(path).unwrap();
+        let (header, _) = CountModel::read_counts(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(weights, header.interpolation_weights);
+        assert_eq!(weights, model.header.unwrap().interpolation_weights);
+    }
+
+    #[test]
+    fn test_trainer4() {
+        let sigma: Sigma = Sigma::new(Some(35), SigmaType::Test);
+        let corpus: String = (0..40).map(|idx| ["aab\n", "abc\n"][idx % 2]).collect();
+        let mut held_out_lines = HeldOutLines::new(&sigma, 2).unwrap();
+        // the held-out line spans two chunks
+        for chunk in [&corpus[..38], &corpus[38..]].iter() {
+            held_out_lines.extend(chunk).unwrap();
+        }
+        let counts = held_out_lines.counts.get_ngram_model(2).unwrap();
+        // lines 9, 19, 29 and 39 are held out, all "abc"
+        assert_eq!(4.0, counts.count("ab"));
+        assert_eq!(4.0, counts.count("bc"));
+        assert_eq!(0.0, counts.count("aa"));
+        assert_eq!(0.0, counts.count("#a"));
+        let trainer = ModelTrainer::new("test", sigma, 2, SmoothingType::LidstoneSmoothing(None));
+        let model = trainer.train(&[&corpus]).unwrap();
+        let pseudo_counts: Vec<f64> = match &model.header.as_ref().unwrap().smoothing_type {
+            SmoothingType::LidstoneSmoothing(Some(pseudo_counts)) => pseudo_counts.clone(),
+            smoothing_type => panic!("unexpected smoothing type: {:?}", smoothing_type),
+        };
+        assert_eq!(2, pseudo_counts.len());
+        // bigrams of the held-out lines follow the training text, their unigrams don't
+        assert!(pseudo_counts[1] < 0.01, "{:?}", pseudo_counts);
+        assert!(pseudo_counts[0] > pseudo_counts[1], "{:?}", pseudo_counts);
+        assert!(model.get("ca").unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_trainer5() {
+        let path = std::env::temp_dir().join("naive_langguesser_trainer_test5.counts");
+        let path = path.to_str().unwrap();
+        let sigma: Sigma = Sigma::new(Some(35), SigmaType::Test);
+        let corpus: String = (0..40).map(|idx| ["aab\n", "abc\n"][idx % 2]).collect();
+        let smoothing_type = SmoothingType::LidstoneSmoothing(None);
+        let mut trainer = ModelTrainer::new("test", sigma, 2, smoothing_type);
+        trainer.add_corpus(&corpus).unwrap();
+        let tuned = trainer.finish().unwrap().header.unwrap().smoothing_type;
+        trainer.write_counts(path).unwrap();
+        let (header, counts) = CountModel::read_counts(path).unwrap();
+        assert_eq!(tuned, header.smoothing_type);
+        // an update keeps the tuned pseudo-counts, in the model and in its counts
+        let mut updated = ModelTrainer::from_counts(header, counts).unwrap();
+        updated.add_corpus("abc\n").unwrap();
+        let model = updated.finish().unwrap();
+        updated.write_counts(path).unwrap();
+        let (header, _) = CountModel::read_counts(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(tuned, header.smoothing_type);
+        assert_eq!(tuned, model.header.unwrap().smoothing_type);
+    }
+}
@@ -1,10 +1,66 @@
-use errors::UtilError;
+use errors::Error;
+use glob::glob;
 use regex::Regex;
+use std::cmp;
 use std::fs;
-use std::path::Path;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::channel;
+use std::thread;
+
+/// Size in bytes of the chunks texts are streamed in
+pub const CHUNK_SIZE: usize = 1 << 20;
+
+/// Read a utf-8 text from a stream in chunks of about `chunk_size` bytes and pass them to `f`
+///
+/// Chunks never split a symbol, bytes of a symbol cut off at the end of a chunk are passed with
+/// the next chunk. Invalid utf-8 is an error.
+pub fn read_chunks<R, F>(mut reader: R, chunk_size: usize, mut f: F) -> Result<(), Error>
+where
+    R: Read,
+    F: FnMut(&str) -> Result<(), Error>,
+{
+    let mut buf: Vec<u8> = vec![0; usize::max(chunk_size, 4)];
+    let mut carry: usize = 0;
+    loop {
+        let read: usize = match reader.read(&mut buf[carry..]) {
+            Ok(read) => read,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::from(err)),
+        };
+        let filled: usize = carry + read;
+        let valid: usize = match std::str::from_utf8(&buf[..filled]) {
+            Ok(_) => filled,
+            // a symbol cut off at the end is completed by the next read
+            Err(err) if err.error_len().is_none() && read > 0 => err.valid_up_to(),
+            Err(err) => {
+                return Err(Error::Io {
+                    path: None,
+                    source: io::Error::new(io::ErrorKind::InvalidData, err),
+                })
+            }
+        };
+        if valid > 0 {
+            // validated above
+            f(std::str::from_utf8(&buf[..valid]).expect("valid utf-8"))?;
+        }
+        if read == 0 {
+            return Ok(());
+        }
+        buf.copy_within(valid..filled, 0);
+        carry = filled - valid;
+    }
+}
+
+/// Read a whole text file, errors name the file
+pub fn read_text(path: &str) -> Result<String, Error> {
+    fs::read_to_string(path).map_err(|err| Error::from(err).in_file(path))
+}
 
 /// Collect paths to all files of a type `.model` from a folder
-pub fn get_model_paths(dir: &str) -> Result<Vec<String>, UtilError> {
+pub fn get_model_paths(dir: &str) -> Result<Vec<String>, Error> {
     let model_path = Path::new(dir);
     let mut model_paths = Vec::new();
     if model_path.is_dir() {
@@ -17,18 +73,18 @@ pub fn get_model_paths(dir: &str) -> Result<Vec<String>, UtilError> {
                 let path_str = String::from(
                     path.to_str()
                         // Option to Result type
-                        .ok_or(UtilError::new("Can't convert path to string."))?,
+                        .ok_or(Error::path("Can't convert path to string."))?,
                 );
                 // only build regex once
                 lazy_static! {
                     static ref IS_MODEL: Regex =
-                        Regex::new(r".*\.model").expect("Can't initialise regex.");
+                        Regex::new(r"\.model$").expect("Can't initialise regex.");
                 }
                 if IS_MODEL.is_match(&path_str[..]) {
                     model_paths.push(String::from(
                         path.to_str()
                             // Option to Result type
-                            .ok_or(UtilError::new("Can't convert path to string."))?,
+                            .ok_or(Error::path("Can't convert path to string."))?,
                     ));
                 }
             }
@@ -37,10 +93,309 @@ pub fn get_model_paths(dir: &str) -> Result<Vec<String>, UtilError> {
     Ok(model_paths)
 }
 
-/// Sort list of tuples by second element
-pub fn sort_by_second_element<T: PartialOrd>(
-    mut vec: Vec<(String, T)>,
-) -> Result<Vec<(String, T)>, UtilError> {
-    vec.sort_by(|tuple1, tuple2| { tuple1.1.partial_cmp(&tuple2.1).unwrap() }.reverse());
-    Ok(vec)
+/// Collect paths to text files from paths to files, directories and glob patterns
+///
+/// Directories are searched recursively, skipping hidden files and directories (e.g.
+/// `.gitkeep`). Paths found in a directory or by a glob pattern are sorted, a path not matching
+/// any file is an error.
+pub fn collect_text_paths(patterns: &[String]) -> Result<Vec<String>, Error> {
+    let mut text_paths: Vec<String> = Vec::new();
+    for pattern in patterns {
+        let path = Path::new(pattern);
+        let mut found: Vec<PathBuf> = Vec::new();
+        if path.is_file() {
+            found.push(path.to_path_buf());
+        } else if path.is_dir() {
+            collect_files(path, &mut found)?;
+        } else {
+            let matches = glob(pattern)
+                .map_err(|err| Error::Path {
+                    details: format!("Illformed glob pattern {}: {}", pattern, err),
+                    source: Some(Box::new(err)),
+                })?;
+            for entry in matches {
+                let entry: PathBuf = entry.map_err(|err| Error::Io {
+                    path: Some(err.path().to_string_lossy().into_owned()),
+                    source: err.into(),
+                })?;
+                match entry.is_dir() {
+                    true => collect_files(&entry, &mut found)?,
+                    false => found.push(entry),
+                }
+            }
+        }
+        if found.is_empty() {
+            return Err(Error::path(&format!("No file found for path: {}", pattern)));
+        }
+        found.sort();
+        for path in found {
+            text_paths.push(String::from(
+                path.to_str()
+                    .ok_or(Error::path("Can't convert path to string."))?,
+            ));
+        }
+    }
+    Ok(text_paths)
+}
+
+/// Collect paths to all files of a directory and its subdirectories, hidden ones excluded
+fn collect_files(dir: &Path, found: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        match path.is_dir() {
+            true => collect_files(&path, found)?,
+            false => found.push(path),
+        }
+    }
+    Ok(())
+}
+
+/// Apply `f` to all items on `workers` threads, keeping the order of the items
+///
+/// Each thread takes the next unprocessed item until all items are processed.
+pub fn parallel_map<T, R, F>(items: &[T], workers: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next: AtomicUsize = AtomicUsize::new(0);
+    let (sender, receiver) = channel::<(usize, R)>();
+    thread::scope(|scope| {
+        for _ in 0..usize::max(1, usize::min(workers, items.len())) {
+            let sender_instance = sender.clone();
+            let (next, f) = (&next, &f);
+            scope.spawn(move || loop {
+                let idx: usize = next.fetch_add(1, Ordering::SeqCst);
+                if idx >= items.len() {
+                    break;
+                }
+                if sender_instance.send((idx, f(&items[idx]))).is_err() {
+                    break;
+                }
+            });
+        }
+    });
+    drop(sender);
+    let mut results: Vec<(usize, R)> = receiver.iter().collect();
+    results.sort_by_key(|(idx, _)| *idx);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Separators of text records read from a stream
+///
+/// # Line
+///
+/// Every line is a record, empty lines included.
+///
+/// # Blank
+///
+/// Records are separated by blank lines, i.e. paragraphs. Empty records are skipped.
+///
+/// # Nul
+///
+/// Records are separated by NUL bytes, e.g. as written by `find -print0`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecordSeparator {
+    Line,
+    Blank,
+    Nul,
+}
+
+impl RecordSeparator {
+    /// Parse record separator from its name as used on the cli
+    pub fn from_name(name: &str) -> Option<RecordSeparator> {
+        match name {
+            "line" => Some(RecordSeparator::Line),
+            "blank" => Some(RecordSeparator::Blank),
+            "nul" => Some(RecordSeparator::Nul),
+            _ => None,
+        }
+    }
+}
+
+/// Iterate text records of a stream, one at a time
+///
+/// Invalid utf-8 is replaced by `U+FFFD` instead of failing the whole stream.
+///
+/// # Fields
+///
+/// * `reader` - buffered stream
+/// * `separator` - separator between records
+pub struct RecordReader<R: BufRead> {
+    reader: R,
+    separator: RecordSeparator,
+}
+
+impl<R: BufRead> RecordReader<R> {
+    pub fn new(reader: R, separator: RecordSeparator) -> RecordReader<R> {
+        RecordReader { reader, separator }
+    }
+
+    /// Read up to and excluding the next `delimiter`, `None` at end of stream
+    fn read_until(&mut self, delimiter: u8) -> io::Result<Option<String>> {
+        let mut buf: Vec<u8> = Vec::new();
+        if self.reader.read_until(delimiter, &mut buf)? == 0 {
+            return Ok(None);
+        }
+        if buf.last() == Some(&delimiter) {
+            buf.pop();
+            // lines might end with \r\n
+            if delimiter == b'\n' && buf.last() == Some(&b'\r') {
+                buf.pop();
+            }
+        }
+        Ok(Some(String::from_utf8_lossy(&buf).into_owned()))
+    }
+}
+
+impl<R: BufRead> Iterator for RecordReader<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.separator {
+            RecordSeparator::Line => self.read_until(b'\n').transpose(),
+            RecordSeparator::Nul => self.read_until(b'\0').transpose(),
+            RecordSeparator::Blank => {
+                let mut record = String::new();
+                loop {
+                    match self.read_until(b'\n') {
+                        Err(err) => return Some(Err(err)),
+                        Ok(None) => break,
+                        Ok(Some(line)) => {
+                            if !line.trim().is_empty() {
+                                if !record.is_empty() {
+                                    record.push('\n');
+                                }
+                                record.push_str(&line);
+                            } else if !record.is_empty() {
+                                break;
+                            }
+                        }
+                    }
+                }
+                match record.is_empty() {
+                    true => None,
+                    false => Some(Ok(record)),
+                }
+            }
+        }
+    }
+}
+
+/// Provide indices of the `k` largest values, largest first
+///
+/// Only the `k` largest values are sorted, the rest is partitioned off in linear time. Ties
+/// are ranked by index, NaN values are ranked last.
+pub fn top_k_indices(values: &[f64], k: usize) -> Vec<usize> {
+    let descending = |idx1: &usize, idx2: &usize| -> cmp::Ordering {
+        match (values[*idx1].is_nan(), values[*idx2].is_nan()) {
+            (false, true) => cmp::Ordering::Less,
+            (true, false) => cmp::Ordering::Greater,
+            _ => values[*idx2]
+                .partial_cmp(&values[*idx1])
+                .unwrap_or(cmp::Ordering::Equal)
+                .then(idx1.cmp(idx2)),
+        }
+    };
+    if k == 1 {
+        return (0..values.len()).min_by(descending).into_iter().collect();
+    }
+    let mut indices: Vec<usize> = (0..values.len()).collect();
+    if k < indices.len() {
+        indices.select_nth_unstable_by(k, descending);
+        indices.truncate(k);
+    }
+    indices.sort_by(descending);
+    indices
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_collect_text_paths() {
+        let paths = collect_text_paths(&[String::from("./data/*.txt")]).unwrap();
+        assert_eq!(
+            vec!["data/english.txt", "data/german.txt", "data/spanish.txt"],
+            paths
+        );
+        let paths = collect_text_paths(&[String::from("./data/models")]).unwrap();
+        assert!(paths.iter().any(|path| path.ends_with("test.model")));
+        assert!(collect_text_paths(&[String::from("./data/missing*.txt")]).is_err());
+        let dir = std::env::temp_dir().join("naive_langguesser_collect_test");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::create_dir_all(dir.join(".hidden")).unwrap();
+        for file in ["a.txt", ".gitkeep", "sub/b.txt", ".hidden/c.txt"].iter() {
+            fs::write(dir.join(file), "text").unwrap();
+        }
+        let paths = collect_text_paths(&[dir.to_string_lossy().into_owned()]).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            vec![dir.join("a.txt"), dir.join("sub").join("b.txt")],
+            paths.iter().map(PathBuf::from).collect::<Vec<PathBuf>>()
+        );
+    }
+
+    #[test]
+    fn test_record_reader() {
+        let records = |input: &[u8], separator: RecordSeparator| -> Vec<String> {
+            RecordReader::new(io::Cursor::new(input.to_vec()), separator)
+                .map(|record| record.unwrap())
+                .collect()
+        };
+        assert_eq!(
+            vec!["one", "", "two"],
+            records(b"one\n\ntwo\r\n", RecordSeparator::Line)
+        );
+        assert_eq!(
+            vec!["one\nline", "two"],
+            records(b"\none\nline\n\n \n\ntwo", RecordSeparator::Blank)
+        );
+        assert_eq!(
+            vec!["one\n", "t\u{FFFD}o"],
+            records(b"one\n\0t\xffo\0", RecordSeparator::Nul)
+        );
+        assert_eq!(Vec::<String>::new(), records(b"", RecordSeparator::Line));
+    }
+
+    #[test]
+    fn test_read_chunks() {
+        let text = "aßc💖d";
+        let mut chunks: Vec<String> = Vec::new();
+        read_chunks(text.as_bytes(), 4, |chunk| {
+            chunks.push(String::from(chunk));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(vec!["aßc", "💖", "d"], chunks);
+        let invalid: &[u8] = &[b'a', 0xff, b'b'];
+        assert!(read_chunks(invalid, 4, |_| Ok(())).is_err());
+        // a truncated symbol at the end of the stream is invalid as well
+        let truncated: &[u8] = &[b'a', 0xc3];
+        assert!(read_chunks(truncated, 4, |_| Ok(())).is_err());
+    }
+
+    #[test]
+    fn test_top_k_indices() {
+        let values: Vec<f64> = vec![-3.0, -1.0, f64::NAN, -2.0, -1.0];
+        assert_eq!(vec![1], top_k_indices(&values, 1));
+        assert_eq!(vec![1, 4, 3], top_k_indices(&values, 3));
+        assert_eq!(vec![1, 4, 3, 0, 2], top_k_indices(&values, 10));
+        assert_eq!(Vec::<usize>::new(), top_k_indices(&values, 0));
+        assert_eq!(Vec::<usize>::new(), top_k_indices(&[], 1));
+    }
+
+    #[test]
+    fn test_parallel_map() {
+        let items: Vec<usize> = (0..100).collect();
+        let squares: Vec<usize> = parallel_map(&items, 4, |item| item * item);
+        assert_eq!(items.iter().map(|item| item * item).collect::<Vec<usize>>(), squares);
+        assert_eq!(Vec::<usize>::new(), parallel_map(&[], 4, |item: &usize| *item));
+    }
 }